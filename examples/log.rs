@@ -12,11 +12,12 @@ fn main() {
 
     let controllers_write = controllers.clone();
     thread::spawn(move || {
+        let mut scanner = gcnctrlusb::Scanner::new().unwrap();
         loop {
-            let mut scanner = gcnctrlusb::Scanner::new().unwrap();
             let mut adapter = if let Some(adapter) = scanner.find_adapter().unwrap() {
                 adapter
             } else {
+                thread::sleep(Duration::from_secs(1));
                 continue;
             };
             let mut listener = adapter.listen().unwrap();
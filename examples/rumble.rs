@@ -0,0 +1,34 @@
+extern crate gcnctrlusb;
+
+// How far an analog trigger must be pressed before its controller starts rumbling.
+const THRESHOLD: u8 = 128;
+
+fn main() {
+    let mut scanner = gcnctrlusb::Scanner::new().unwrap();
+    let mut adapter = scanner.find_adapter().unwrap().expect("no adapter found");
+    let mut listener = adapter.listen().unwrap();
+
+    println!("Press L or R to rumble. Press Start on any controller to exit.");
+
+    let mut rumbling = [false; 4];
+
+    while let Ok(controllers) = listener.read() {
+        let mut wanted = [false; 4];
+        for (port, controller) in controllers.iter().enumerate() {
+            if let Some(ref controller) = *controller {
+                if controller.start {
+                    // Dropping the listener stops any rumbling ports.
+                    return;
+                }
+
+                wanted[port] = controller.l_analog > THRESHOLD || controller.r_analog > THRESHOLD;
+            }
+        }
+
+        // The adapter keeps rumbling until told otherwise, so only send a command on changes.
+        if wanted != rumbling {
+            listener.rumble(wanted).unwrap();
+            rumbling = wanted;
+        }
+    }
+}
@@ -0,0 +1,21 @@
+extern crate gcnctrlusb;
+extern crate tracing_subscriber;
+
+use tracing_subscriber::fmt::format::FmtSpan;
+
+// Run with `cargo run --example trace --features tracing`.
+fn main() {
+    // Print every span as it closes, along with its recorded fields and how long it took.
+    tracing_subscriber::fmt()
+        .with_max_level(tracing_subscriber::filter::LevelFilter::TRACE)
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+
+    let mut scanner = gcnctrlusb::Scanner::new().unwrap();
+    let mut adapter = scanner.find_adapter().unwrap().expect("no adapter found");
+    let mut listener = adapter.listen().unwrap();
+
+    for _ in 0..10 {
+        listener.read().unwrap();
+    }
+}
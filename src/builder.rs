@@ -0,0 +1,180 @@
+use {Controller, ControllerKind};
+
+/// Assembles a `Controller` through chainable setters, which is more legible than a struct literal
+/// when writing tests or synthesizing input.
+///
+/// Every field that is not set keeps its neutral value: a wired controller with no buttons held,
+/// both joysticks centered at `127`, and both analog triggers at `0`.
+///
+/// ```norun
+/// let controller = gcnctrlusb::ControllerBuilder::new().a(true).stick(200, 127).build();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ControllerBuilder {
+    controller: Controller,
+}
+
+impl ControllerBuilder {
+    /// Returns a builder for a neutral controller.
+    pub fn new() -> ControllerBuilder {
+        ControllerBuilder {
+            controller: Controller {
+                kind: ControllerKind::Wired,
+                a: false,
+                b: false,
+                x: false,
+                y: false,
+                up: false,
+                down: false,
+                left: false,
+                right: false,
+                l: false,
+                r: false,
+                l_analog: 0,
+                r_analog: 0,
+                z: false,
+                start: false,
+                stick_x: 127,
+                stick_y: 127,
+                c_stick_x: 127,
+                c_stick_y: 127,
+            },
+        }
+    }
+
+    /// Sets the classification of the controller.
+    pub fn kind(mut self, kind: ControllerKind) -> ControllerBuilder {
+        self.controller.kind = kind;
+        self
+    }
+
+    /// Sets the "A" button status.
+    pub fn a(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.a = pressed;
+        self
+    }
+
+    /// Sets the "B" button status.
+    pub fn b(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.b = pressed;
+        self
+    }
+
+    /// Sets the "X" button status.
+    pub fn x(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.x = pressed;
+        self
+    }
+
+    /// Sets the "Y" button status.
+    pub fn y(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.y = pressed;
+        self
+    }
+
+    /// Sets the directional pad up button status.
+    pub fn up(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.up = pressed;
+        self
+    }
+
+    /// Sets the directional pad down button status.
+    pub fn down(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.down = pressed;
+        self
+    }
+
+    /// Sets the directional pad left button status.
+    pub fn left(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.left = pressed;
+        self
+    }
+
+    /// Sets the directional pad right button status.
+    pub fn right(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.right = pressed;
+        self
+    }
+
+    /// Sets the digital "L" button status.
+    pub fn l(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.l = pressed;
+        self
+    }
+
+    /// Sets the digital "R" button status.
+    pub fn r(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.r = pressed;
+        self
+    }
+
+    /// Sets the "Z" button status.
+    pub fn z(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.z = pressed;
+        self
+    }
+
+    /// Sets the Start button status.
+    pub fn start(mut self, pressed: bool) -> ControllerBuilder {
+        self.controller.start = pressed;
+        self
+    }
+
+    /// Sets the level of depression of the analog "L" button.
+    pub fn l_analog(mut self, value: u8) -> ControllerBuilder {
+        self.controller.l_analog = value;
+        self
+    }
+
+    /// Sets the level of depression of the analog "R" button.
+    pub fn r_analog(mut self, value: u8) -> ControllerBuilder {
+        self.controller.r_analog = value;
+        self
+    }
+
+    /// Sets the position of the primary analog joystick.
+    pub fn stick(mut self, x: u8, y: u8) -> ControllerBuilder {
+        self.controller.stick_x = x;
+        self.controller.stick_y = y;
+        self
+    }
+
+    /// Sets the position of the secondary ("C") analog joystick.
+    pub fn c_stick(mut self, x: u8, y: u8) -> ControllerBuilder {
+        self.controller.c_stick_x = x;
+        self.controller.c_stick_y = y;
+        self
+    }
+
+    /// Returns the assembled controller state.
+    pub fn build(&self) -> Controller {
+        self.controller
+    }
+}
+
+impl Default for ControllerBuilder {
+    fn default() -> ControllerBuilder {
+        ControllerBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_sets_fields() {
+        let controller = ControllerBuilder::new()
+            .a(true)
+            .z(true)
+            .stick(200, 127)
+            .l_analog(180)
+            .build();
+        assert_eq!(controller.kind, ControllerKind::Wired);
+        assert!(controller.a && controller.z);
+        assert!(!controller.b && !controller.start);
+        assert_eq!((controller.stick_x, controller.stick_y), (200, 127));
+        assert_eq!((controller.c_stick_x, controller.c_stick_y), (127, 127));
+        assert_eq!((controller.l_analog, controller.r_analog), (180, 0));
+    }
+}
@@ -0,0 +1,229 @@
+use std::f32::consts;
+use Controller;
+
+/// The observed range of one stick axis: its lowest value, its value at rest, and its highest
+/// value.
+///
+/// Worn or third-party sticks are often asymmetric, with the center not halfway between the
+/// extremes, so each half of the axis is scaled separately. See `normalize`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AxisCalib {
+    /// The value at full deflection towards the negative end.
+    pub min: u8,
+    /// The value at rest.
+    pub center: u8,
+    /// The value at full deflection towards the positive end.
+    pub max: u8,
+}
+
+impl AxisCalib {
+    /// Maps `value` to `-1.0..1.0` piecewise: `min..center` is scaled linearly onto `-1.0..0.0`
+    /// and `center..max` onto `0.0..1.0`.
+    ///
+    /// Values beyond `min` or `max` are clamped to `-1.0` or `1.0`, respectively.
+    pub fn normalize(&self, value: u8) -> f32 {
+        let (value, min, center, max) =
+            (value as f32, self.min as f32, self.center as f32, self.max as f32);
+
+        if value < center {
+            if value <= min { -1.0 } else { (value - center) / (center - min) }
+        } else if value > center {
+            if value >= max { 1.0 } else { (value - center) / (max - center) }
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for AxisCalib {
+    /// Returns the full range of a `u8`, centered on `127`.
+    fn default() -> AxisCalib {
+        AxisCalib {
+            min: 0,
+            center: 127,
+            max: 255,
+        }
+    }
+}
+
+/// The calibration of every analog input of a controller, used to normalize its states.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ControllerCalibration {
+    /// The x-axis of the primary analog joystick.
+    pub stick_x: AxisCalib,
+    /// The y-axis of the primary analog joystick.
+    pub stick_y: AxisCalib,
+    /// The x-axis of the secondary ("C") analog joystick.
+    pub c_stick_x: AxisCalib,
+    /// The y-axis of the secondary ("C") analog joystick.
+    pub c_stick_y: AxisCalib,
+    /// The value of the analog "L" button at rest.
+    pub l_floor: u8,
+    /// The value of the analog "R" button at rest.
+    pub r_floor: u8,
+}
+
+impl ControllerCalibration {
+    /// Returns the analog inputs of `controller` normalized according to this calibration.
+    ///
+    /// Stick axes are mapped to `-1.0..1.0` as described in `AxisCalib::normalize`. Triggers are
+    /// mapped from `floor..255` to `0.0..1.0`, with values at or below the floor reading `0.0`.
+    pub fn normalize(&self, controller: &Controller) -> NormalizedController {
+        fn trigger(value: u8, floor: u8) -> f32 {
+            if value <= floor {
+                0.0
+            } else {
+                (value - floor) as f32 / (255 - floor) as f32
+            }
+        }
+
+        NormalizedController {
+            stick_x: self.stick_x.normalize(controller.stick_x),
+            stick_y: self.stick_y.normalize(controller.stick_y),
+            c_stick_x: self.c_stick_x.normalize(controller.c_stick_x),
+            c_stick_y: self.c_stick_y.normalize(controller.c_stick_y),
+            l_analog: trigger(controller.l_analog, self.l_floor),
+            r_analog: trigger(controller.r_analog, self.r_floor),
+        }
+    }
+}
+
+/// The analog inputs of a controller normalized by `ControllerCalibration::normalize`.
+///
+/// Digital inputs are not affected by calibration and remain available on the `Controller`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NormalizedController {
+    /// The x-axis of the primary analog joystick, from `-1.0` (left) to `1.0` (right).
+    pub stick_x: f32,
+    /// The y-axis of the primary analog joystick, from `-1.0` (down) to `1.0` (up).
+    pub stick_y: f32,
+    /// The x-axis of the secondary ("C") analog joystick, from `-1.0` (left) to `1.0` (right).
+    pub c_stick_x: f32,
+    /// The y-axis of the secondary ("C") analog joystick, from `-1.0` (down) to `1.0` (up).
+    pub c_stick_y: f32,
+    /// The level of depression of the analog "L" button, from `0.0` to `1.0`.
+    pub l_analog: f32,
+    /// The level of depression of the analog "R" button, from `0.0` to `1.0`.
+    pub r_analog: f32,
+}
+
+impl NormalizedController {
+    /// Multiplies the axes of the primary joystick by `stick`, those of the secondary joystick by
+    /// `c_stick`, and both triggers by `triggers`, then clamps every value back to its range.
+    ///
+    /// This adjusts the sensitivity of each input separately. Factors above `1.0` make an input
+    /// saturate at its bound before it is fully deflected, so the remainder of its travel has no
+    /// effect.
+    pub fn scale_axes(&mut self, stick: f32, c_stick: f32, triggers: f32) {
+        fn clamp(value: f32, min: f32) -> f32 {
+            value.max(min).min(1.0)
+        }
+
+        self.stick_x = clamp(self.stick_x * stick, -1.0);
+        self.stick_y = clamp(self.stick_y * stick, -1.0);
+        self.c_stick_x = clamp(self.c_stick_x * c_stick, -1.0);
+        self.c_stick_y = clamp(self.c_stick_y * c_stick, -1.0);
+        self.l_analog = clamp(self.l_analog * triggers, 0.0);
+        self.r_analog = clamp(self.r_analog * triggers, 0.0);
+    }
+
+    /// Stretches both joysticks so that their octagonal gate maps onto the unit circle, making
+    /// every direction reach the same magnitude at full deflection.
+    ///
+    /// The gate is approximated as a regular octagon with its corners at the eight notches (the
+    /// cardinal and diagonal directions) and a distance of `1.0` from the center to each corner,
+    /// which is what a calibrated stick reports at the cardinal notches. Along a direction `θ`, the
+    /// octagon's edge lies at a distance of `cos(π/8) / cos(φ - π/8)`, where `φ` is `θ` modulo
+    /// `π/4`. Each position is divided by that distance, so the notches are left as they are and
+    /// positions between them are pushed outwards by up to about 8%. The resulting magnitude is
+    /// clamped to `1.0`, and the direction of each stick is preserved.
+    pub fn linearize_gate(&mut self) {
+        fn linearize(x: &mut f32, y: &mut f32) {
+            let magnitude = (*x * *x + *y * *y).sqrt();
+            if magnitude == 0.0 {
+                return;
+            }
+
+            let eighth = consts::FRAC_PI_4;
+            let sixteenth = consts::FRAC_PI_8;
+            let angle = y.atan2(*x);
+            let phi = angle - (angle / eighth).floor() * eighth;
+            let edge = sixteenth.cos() / (phi - sixteenth).cos();
+            let scale = (magnitude / edge).min(1.0) / magnitude;
+            *x *= scale;
+            *y *= scale;
+        }
+
+        linearize(&mut self.stick_x, &mut self.stick_y);
+        linearize(&mut self.c_stick_x, &mut self.c_stick_y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(stick_x: f32, stick_y: f32) -> NormalizedController {
+        NormalizedController {
+            stick_x: stick_x,
+            stick_y: stick_y,
+            c_stick_x: 0.5,
+            c_stick_y: -0.5,
+            l_analog: 0.25,
+            r_analog: 0.75,
+        }
+    }
+
+    #[test]
+    fn scale_axes_clamps() {
+        let mut controller = normalized(0.75, -0.25);
+        controller.scale_axes(2.0, 1.0, 2.0);
+        assert_eq!(controller.stick_x, 1.0);
+        assert_eq!(controller.stick_y, -0.5);
+        assert_eq!(controller.c_stick_x, 0.5);
+        assert_eq!(controller.c_stick_y, -0.5);
+        assert_eq!(controller.l_analog, 0.5);
+        assert_eq!(controller.r_analog, 1.0);
+
+        let mut controller = normalized(-0.75, 0.0);
+        controller.scale_axes(2.0, 1.0, 1.0);
+        assert_eq!(controller.stick_x, -1.0);
+    }
+
+    #[test]
+    fn linearize_gate_equalizes_magnitudes() {
+        fn magnitude(c: &NormalizedController) -> f32 {
+            (c.stick_x.powi(2) + c.stick_y.powi(2)).sqrt()
+        }
+
+        let edge = consts::FRAC_PI_8.cos();
+
+        // A cardinal notch, a diagonal notch, and the middle of the gate edge between them.
+        let mut cardinal = normalized(1.0, 0.0);
+        let mut diagonal = normalized(consts::FRAC_1_SQRT_2, consts::FRAC_1_SQRT_2);
+        let (sin, cos) = consts::FRAC_PI_8.sin_cos();
+        let mut between = normalized(edge * cos, edge * sin);
+        assert!(magnitude(&between) < 0.95);
+
+        for controller in [&mut cardinal, &mut diagonal, &mut between].iter_mut() {
+            controller.linearize_gate();
+        }
+
+        assert!((magnitude(&cardinal) - 1.0).abs() < 1e-5);
+        assert!((magnitude(&diagonal) - magnitude(&cardinal)).abs() < 1e-5);
+        assert!((magnitude(&between) - magnitude(&cardinal)).abs() < 1e-5);
+        assert!((between.stick_y / between.stick_x - consts::FRAC_PI_8.tan()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn asymmetric_axis() {
+        let axis = AxisCalib { min: 30, center: 120, max: 230 };
+        assert_eq!(axis.normalize(30), -1.0);
+        assert_eq!(axis.normalize(75), -0.5);
+        assert_eq!(axis.normalize(120), 0.0);
+        assert_eq!(axis.normalize(175), 0.5);
+        assert_eq!(axis.normalize(230), 1.0);
+        assert_eq!(axis.normalize(0), -1.0);
+        assert_eq!(axis.normalize(255), 1.0);
+    }
+}
@@ -0,0 +1,89 @@
+use {Axis, Button, Controller, ControllerKind, Controllers, Error, Listener, Port};
+
+/// A change in the input of a single controller port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputEvent {
+    /// A controller of the given classification was plugged in (or a wireless controller was
+    /// paired).
+    Connected(ControllerKind),
+    /// The controller was removed.
+    Disconnected,
+    /// A digital button was pressed.
+    ButtonDown(Button),
+    /// A digital button was released.
+    ButtonUp(Button),
+    /// An analog input moved to the given value.
+    AxisMoved(Axis, u8),
+}
+
+/// Reads controller data and reports what changed since the previous read.
+///
+/// The poller owns a `Listener` and remembers the state of each port between calls to `poll`.
+pub struct EventPoller<'a> {
+    listener: Listener<'a>,
+    previous: [Option<Controller>; 4],
+    threshold: u8,
+}
+
+impl<'a> EventPoller<'a> {
+    /// Wraps a `Listener`, treating every port as empty until the first poll.
+    ///
+    /// Analog inputs are only reported once they move by more than `threshold` from the value
+    /// last reported for them, which filters out the jitter of a resting joystick. A `threshold`
+    /// of `0` reports every change. Because movement is measured against the last reported value
+    /// rather than the previous packet, slow movements are still reported once they accumulate.
+    pub fn new(listener: Listener<'a>, threshold: u8) -> EventPoller<'a> {
+        EventPoller {
+            listener: listener,
+            previous: [None; 4],
+            threshold: threshold,
+        }
+    }
+
+    /// Changes the analog threshold. See `EventPoller::new`.
+    pub fn set_threshold(&mut self, threshold: u8) {
+        self.threshold = threshold;
+    }
+
+    /// Reads a data packet and returns every event that occurred since the previous poll.
+    ///
+    /// Events are ordered as described by `Controllers::diff`. A `Connected` event is reported
+    /// alone on the packet in which the controller appears, and the inputs held at that moment
+    /// become the baseline for later events.
+    ///
+    /// Errors are those of `Listener::read`; the remembered state is left untouched when one
+    /// occurs.
+    pub fn poll(&mut self) -> Result<Vec<(Port, InputEvent)>, Error> {
+        let current = try!(self.listener.read());
+        if Controllers(current).is_empty() && Controllers(self.previous).is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let events = Controllers(current).diff(&Controllers(self.previous), self.threshold);
+
+        for (previous, current) in self.previous.iter_mut().zip(current.iter()) {
+            *previous = match (*previous, *current) {
+                (Some(old), Some(new)) => {
+                    let mut baseline = new;
+                    // Unreported axes keep their old baseline so that slow drift accumulates.
+                    for &axis in Axis::ALL.iter() {
+                        let (old, new) = (old.axis(axis), new.axis(axis));
+                        let delta = if new > old { new - old } else { old - new };
+                        if delta <= self.threshold {
+                            *baseline.axis_mut(axis) = old;
+                        }
+                    }
+                    Some(baseline)
+                },
+                (_, current) => current,
+            };
+        }
+
+        Ok(events)
+    }
+
+    /// Returns the wrapped `Listener`.
+    pub fn into_inner(self) -> Listener<'a> {
+        self.listener
+    }
+}
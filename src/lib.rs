@@ -6,7 +6,34 @@
 //! This library depends on `libusb`, which is available as a dynamic library on many platforms
 //! including Linux, Windows, and Mac OS X.
 //!
-//! Currently, rumble commands are **unimplemented**.
+//! Rumble is supported through `Listener::rumble`. Note that the official adapter only powers the
+//! rumble motors when its second (grey) USB plug is connected.
+//!
+//! Rumble support can be compiled out by disabling the default `rumble` feature, for applications
+//! that only read input. This removes the rumble methods of `Listener` along with
+//! `RumblePattern` and `RumblePlayer`, and a dropped `Listener` no longer sends a command to stop
+//! rumbling. Input is read the same way either way: the adapter's output endpoint is still used
+//! once by `Adapter::listen` to start the flow of data, but its address is not kept.
+//!
+//! No public function of this library panics on malformed input from the adapter. Packets of an
+//! unexpected length are reported as `Error::InvalidPacket`, and only fixed-size buffers ever
+//! reach the parser.
+//!
+//! # Tracing
+//!
+//! With the `tracing` feature enabled, USB operations are instrumented with spans from the
+//! `tracing` crate, all with the `gcnctrlusb` target:
+//!
+//! * `listen` (debug level) around `Adapter::listen`, with the `interface`, `endpoint_in`, and
+//!   `endpoint_out` fields recorded once they are known.
+//! * `read` (trace level) around every packet read from the adapter, with the `endpoint`, the
+//!   number of `bytes` read (if the transfer succeeded), and the `elapsed_us` microseconds the
+//!   transfer took.
+//! * `rumble` (debug level) around every rumble command sent to the adapter, with the `endpoint`,
+//!   the `intensities` sent, and the `elapsed_us` microseconds the transfer took.
+//!
+//! Without the feature, no instrumentation is compiled in. See `examples/trace.rs` for printing
+//! the spans with a `tracing-subscriber` formatter.
 //!
 //! # Usage
 //!
@@ -28,17 +55,52 @@
 //! ```
 
 extern crate libusb;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+mod builder;
+mod calibration;
+mod event;
+mod paced;
+mod pipeline;
+#[cfg(feature = "rumble")]
+mod rumble;
+mod stability;
+mod tracker;
+mod watchdog;
+
+pub use builder::ControllerBuilder;
+pub use calibration::{AxisCalib, ControllerCalibration, NormalizedController};
+pub use event::{EventPoller, InputEvent};
+pub use paced::PacedReader;
+pub use pipeline::{ClampAnalog, FramePipeline, FrameProcessor};
+#[cfg(feature = "rumble")]
+pub use rumble::{RumblePattern, RumblePlayer};
+pub use stability::StabilityDetector;
+pub use tracker::{ControllerId, PortTracker};
+pub use watchdog::Watchdog;
 
 use libusb::{Context, Device, DeviceHandle};
+use std::cmp;
 use std::error::Error as StdError;
 use std::fmt::Error as FmtError;
 use std::fmt::{Display, Formatter};
-use std::time::Duration;
+use std::io::{self, Write};
+use std::slice::Chunks;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const VENDOR_ID: u16 = 0x057e;
 const PRODUCT_ID: u16 = 0x0337;
+const PACKET_LEN: usize = 37;
+const REPORT_ID: u8 = 0x21;
+const MAX_DRAINED_PACKETS: usize = 8;
 
 /// Searches for GameCube controller adapter USB devices.
+///
+/// A single `Scanner` can be kept for the lifetime of an application. Every search asks `libusb`
+/// for a fresh list of devices, so adapters plugged in after the `Scanner` was created are found
+/// without recreating it.
 pub struct Scanner {
     context: Context,
 }
@@ -52,97 +114,563 @@ impl Scanner {
     }
 
     /// Returns the first adapter found, or `None` if no adapter was found.
+    ///
+    /// The USB bus is enumerated anew on each call, so this can be called repeatedly (for example
+    /// while waiting for an adapter to be plugged in) once any previously returned `Adapter` has
+    /// been dropped.
+    ///
+    /// Devices whose descriptors cannot be read are skipped, so a misbehaving peripheral elsewhere
+    /// on the bus does not prevent the adapter from being found. An error is only returned if the
+    /// list of devices itself cannot be retrieved.
     pub fn find_adapter<'a>(&'a mut self) -> Result<Option<Adapter<'a>>, Error> {
+        self.find_adapter_where(|_| true)
+    }
+
+    /// Returns the first adapter found for which `predicate` returns `true`, or `None` if no such
+    /// adapter was found.
+    ///
+    /// This behaves like `find_adapter`, except that each device matching the adapter's vendor and
+    /// product IDs is additionally passed to `predicate`, for example to pick the adapter on a
+    /// specific bus with `|device| device.bus_number() == 2`. The device is passed by mutable
+    /// reference because `libusb` requires one to query it, and it is only borrowed for the
+    /// duration of the call, so the predicate cannot keep it.
+    pub fn find_adapter_where<'a, F>(&'a mut self, mut predicate: F)
+        -> Result<Option<Adapter<'a>>, Error>
+        where F: FnMut(&mut Device<'a>) -> bool
+    {
         for mut device in try!(self.context.devices()).iter() {
-            let desc = try!(device.device_descriptor());
+            let desc = match device.device_descriptor() {
+                Ok(desc) => desc,
+                Err(_) => continue,
+            };
 
-            if desc.vendor_id() == VENDOR_ID && desc.product_id() == PRODUCT_ID {
-                return Ok(Some(Adapter { device: device }));
+            if desc.vendor_id() == VENDOR_ID && desc.product_id() == PRODUCT_ID &&
+               predicate(&mut device) {
+                return Ok(Some(Adapter { device: device, interface_number: None }));
             }
         }
 
         Ok(None)
     }
+
+    /// Describes every device matching the adapter's vendor and product IDs.
+    ///
+    /// This is meant for diagnostics, such as including details about a clone adapter in a bug
+    /// report, and not for regular use: each device is briefly opened to read its string
+    /// descriptors. Any string that cannot be read, for example because the device is in use or
+    /// inaccessible, is left as `None`.
+    pub fn describe_devices(&mut self) -> Result<Vec<DeviceDescription>, Error> {
+        let mut descriptions = Vec::new();
+
+        for mut device in try!(self.context.devices()).iter() {
+            let desc = match device.device_descriptor() {
+                Ok(desc) => desc,
+                Err(_) => continue,
+            };
+
+            if desc.vendor_id() != VENDOR_ID || desc.product_id() != PRODUCT_ID {
+                continue;
+            }
+
+            descriptions.push(describe_device(&mut device, &desc));
+        }
+
+        Ok(descriptions)
+    }
+
+    /// Checks every adapter in turn by opening it, reading a single packet, and closing it again,
+    /// reporting which ports have controllers connected.
+    ///
+    /// This is a one-shot diagnostic ("is everything working?") rather than a way to read input.
+    /// A failure to open or read an adapter is recorded in its report instead of aborting the
+    /// check, so an error is only returned if the list of devices cannot be retrieved. Adapters
+    /// that are in use, for example by a `Listener` elsewhere, are reported with
+    /// `Error::DeviceBusy`.
+    pub fn diagnose(&mut self) -> Result<Vec<AdapterReport>, Error> {
+        let mut reports = Vec::new();
+
+        for mut device in try!(self.context.devices()).iter() {
+            let desc = match device.device_descriptor() {
+                Ok(desc) => desc,
+                Err(_) => continue,
+            };
+
+            if desc.vendor_id() != VENDOR_ID || desc.product_id() != PRODUCT_ID {
+                continue;
+            }
+
+            let description = describe_device(&mut device, &desc);
+            let mut adapter = Adapter { device: device, interface_number: None };
+            let read = adapter.listen().and_then(|mut listener| listener.read());
+
+            reports.push(match read {
+                Ok(c) => AdapterReport {
+                    description: description,
+                    kinds: [
+                        c[0].map(|c| c.kind),
+                        c[1].map(|c| c.kind),
+                        c[2].map(|c| c.kind),
+                        c[3].map(|c| c.kind),
+                    ],
+                    error: None,
+                },
+                Err(err) => AdapterReport {
+                    description: description,
+                    kinds: [None; 4],
+                    error: Some(err),
+                },
+            });
+        }
+
+        Ok(reports)
+    }
+}
+
+// Gathers the details of a device, opening it to read its string descriptors if possible.
+fn describe_device(device: &mut Device, desc: &libusb::DeviceDescriptor) -> DeviceDescription {
+    let timeout = Duration::from_secs(1);
+    let mut description = DeviceDescription {
+        vendor_id: desc.vendor_id(),
+        product_id: desc.product_id(),
+        bus_number: device.bus_number(),
+        address: device.address(),
+        bcd_device: bcd_device(desc),
+        manufacturer: None,
+        product: None,
+        serial_number: None,
+    };
+
+    if let Ok(mut handle) = device.open() {
+        let language = handle.read_languages(timeout).ok()
+            .and_then(|languages| languages.first().cloned());
+        if let Some(language) = language {
+            description.manufacturer =
+                handle.read_manufacturer_string(language, desc, timeout).ok();
+            description.product = handle.read_product_string(language, desc, timeout).ok();
+            description.serial_number =
+                handle.read_serial_number_string(language, desc, timeout).ok();
+        }
+    }
+
+    description
+}
+
+/// The outcome of checking a single adapter with `Scanner::diagnose`.
+#[derive(Debug)]
+pub struct AdapterReport {
+    /// Identifying details of the adapter.
+    pub description: DeviceDescription,
+    /// The classification of the controller connected to each port, or `None` for empty ports.
+    /// Every port is `None` if the adapter could not be read.
+    pub kinds: [Option<ControllerKind>; 4],
+    /// The error that prevented the adapter from being opened or read, if any.
+    pub error: Option<Error>,
+}
+
+/// Identifying details of an adapter USB device, as returned by `Scanner::describe_devices`.
+#[derive(Clone, Debug)]
+pub struct DeviceDescription {
+    /// The USB vendor ID.
+    pub vendor_id: u16,
+    /// The USB product ID.
+    pub product_id: u16,
+    /// The number of the bus the device is connected to.
+    pub bus_number: u8,
+    /// The address of the device on its bus.
+    pub address: u8,
+    /// The device release number (`bcdDevice`) in binary-coded decimal, which identifies the
+    /// firmware revision. See `firmware_version_string`.
+    pub bcd_device: u16,
+    /// The manufacturer string, if it could be read.
+    pub manufacturer: Option<String>,
+    /// The product string, if it could be read.
+    pub product: Option<String>,
+    /// The serial number string, if it could be read.
+    pub serial_number: Option<String>,
+}
+
+impl DeviceDescription {
+    /// Returns the firmware revision (`bcd_device`) formatted as `major.minor`, such as `"1.03"`.
+    pub fn firmware_version_string(&self) -> String {
+        format_bcd(self.bcd_device)
+    }
+}
+
+/// What is known about an adapter's capabilities, as returned by `Adapter::capabilities`.
+///
+/// Some fields are authoritative while others are inferred; see `Adapter::capabilities`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdapterCapabilities {
+    /// Whether rumble commands can be sent to the adapter. This is inferred.
+    pub rumble_supported: bool,
+    /// The number of controller ports. This is authoritative.
+    pub port_count: usize,
+    /// The kind of adapter. This is inferred.
+    pub variant: AdapterVariant,
+}
+
+/// The kind of an adapter, such as the official adapter or a particular clone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AdapterVariant {
+    /// The kind of adapter could not be determined.
+    ///
+    /// The official adapter and the supported clones share the same vendor and product IDs, and
+    /// this library cannot yet tell them apart, so every adapter is currently reported as
+    /// `Unknown`. Other variants will be added once they can be detected.
+    Unknown,
+}
+
+// Formats a binary-coded decimal release number. Each nibble holds one decimal digit, so the
+// hexadecimal representation reads as the decimal version.
+fn format_bcd(bcd: u16) -> String {
+    format!("{:x}.{:02x}", bcd >> 8, bcd & 0xff)
+}
+
+// `libusb` only exposes the decoded device release number, so it is encoded back into its raw
+// binary-coded decimal form.
+fn bcd_device(desc: &libusb::DeviceDescriptor) -> u16 {
+    let version = desc.device_version();
+    let (major, minor, sub_minor) = (version.major() as u16, version.minor() as u16,
+                                     version.sub_minor() as u16);
+    (major / 10) << 12 | (major % 10) << 8 | minor << 4 | sub_minor
+}
+
+// The endpoints that `Adapter::listen` uses, along with the interface setting they belong to.
+struct Endpoints {
+    interface: u8,
+    setting: u8,
+    endpoint_in: u8,
+    max_packet_size: u16,
+    endpoint_out: Option<u8>,
+    ambiguous: bool,
+}
+
+// Selects the interface setting and endpoints to read controller data through, optionally
+// restricted to the interface with the given number.
+//
+// Within each interface, the first alternate setting with an input endpoint is chosen. If several
+// interfaces qualify, the last one wins.
+fn find_endpoints(config: &libusb::ConfigDescriptor, interface_number: Option<u8>)
+    -> Option<Endpoints>
+{
+    let mut found = None;
+
+    for interface in config.interfaces() {
+        if interface_number.map_or(false, |number| interface.number() != number) {
+            continue;
+        }
+
+        if let Some(endpoints) = interface.descriptors().filter_map(setting_endpoints).next() {
+            found = Some(endpoints);
+        }
+    }
+
+    found
+}
+
+// Selects the endpoints of a single interface setting, or returns `None` if it has no input
+// endpoint. Of several endpoints in the same direction, the first interrupt endpoint is preferred,
+// falling back on the first endpoint of any other type.
+fn setting_endpoints(desc: libusb::InterfaceDescriptor) -> Option<Endpoints> {
+    let mut endpoint_in: Option<(u8, bool, u16)> = None;
+    let mut endpoint_out: Option<(u8, bool, u16)> = None;
+    let mut in_count = 0;
+    let mut out_count = 0;
+
+    for endpoint in desc.endpoint_descriptors() {
+        let interrupt = endpoint.transfer_type() == libusb::TransferType::Interrupt;
+        let (chosen, count) = match endpoint.direction() {
+            libusb::Direction::In => (&mut endpoint_in, &mut in_count),
+            libusb::Direction::Out => (&mut endpoint_out, &mut out_count),
+        };
+        *count += 1;
+        if chosen.map_or(true, |(_, was_interrupt, _)| interrupt && !was_interrupt) {
+            *chosen = Some((endpoint.address(), interrupt, endpoint.max_packet_size()));
+        }
+    }
+
+    endpoint_in.map(|(address, _, max_packet_size)| {
+        Endpoints {
+            interface: desc.interface_number(),
+            setting: desc.setting_number(),
+            endpoint_in: address,
+            max_packet_size: max_packet_size,
+            endpoint_out: endpoint_out.map(|(address, _, _)| address),
+            ambiguous: in_count > 1 || out_count > 1,
+        }
+    })
 }
 
 /// A wrapper around the unopened USB device.
 pub struct Adapter<'a> {
     device: Device<'a>,
+    interface_number: Option<u8>,
 }
 
 impl<'a> Adapter<'a> {
     /// Opens the USB device and initializes the hardware for reading controller data.
     ///
     /// If the device is inaccessible or unrecognizable, an error is returned. For example, the
-    /// device will be inaccessible if a previous `Listener` for this adapter is still alive.
+    /// device will be inaccessible if a previous `Listener` for this adapter is still alive. When
+    /// the driver reports that the device is held by someone else, `Error::DeviceBusy` is returned.
+    ///
+    /// Adapters that only expose an input endpoint are opened read-only; see
+    /// `Listener::is_read_only`.
     pub fn listen(&mut self) -> Result<Listener<'a>, Error> {
-        let mut handle = try!(self.device.open());
-
-        let config = try!(self.device.config_descriptor(0));
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("listen", interface = tracing::field::Empty,
+                                        endpoint_in = tracing::field::Empty,
+                                        endpoint_out = tracing::field::Empty);
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
 
-        let mut interface_descriptor: Option<_> = None;
-        let mut endpoint_in = None;
-        let mut endpoint_out = None;
-
-        for interface in config.interfaces() {
-            interface_descriptor = None;
-            endpoint_in = None;
-            endpoint_out = None;
-            for desc in interface.descriptors() {
-                for endpoint in desc.endpoint_descriptors() {
-                    match endpoint.direction() {
-                        libusb::Direction::In => endpoint_in = Some(endpoint.address()),
-                        libusb::Direction::Out => endpoint_out = Some(endpoint.address()),
-                    }
-                }
-                interface_descriptor = Some(desc);
-            }
-        }
+        let mut handle = try!(self.device.open().map_err(busy_error));
 
-        if interface_descriptor.is_none() || endpoint_in.is_none() || endpoint_out.is_none() {
-            return Err(Error::UnrecognizedProtocol);
-        }
+        let bcd_device = bcd_device(&try!(self.device.device_descriptor()));
+        let config = try!(self.device.config_descriptor(0));
 
-        let interface_descriptor = interface_descriptor.unwrap();
-        let interface_number = interface_descriptor.interface_number();
+        let endpoints = match find_endpoints(&config, self.interface_number) {
+            Some(endpoints) => endpoints,
+            None => return Err(Error::UnrecognizedProtocol),
+        };
+        let interface_number = endpoints.interface;
 
         let has_kernel_driver = match handle.kernel_driver_active(interface_number) {
             Ok(true) => {
-                try!(handle.detach_kernel_driver(interface_number));
+                try!(handle.detach_kernel_driver(interface_number).map_err(busy_error));
                 true
             },
             _ => false,
         };
 
-        try!(handle.set_active_configuration(config.number()));
-        try!(handle.claim_interface(interface_number));
-        let setting = interface_descriptor.setting_number();
-        try!(handle.set_alternate_setting(interface_number, setting));
+        try!(handle.set_active_configuration(config.number()).map_err(busy_error));
+        try!(handle.claim_interface(interface_number).map_err(busy_error));
+        try!(handle.set_alternate_setting(interface_number, endpoints.setting));
 
-        // Tell the adapter to start sending packets.
-        let timeout = Duration::from_secs(1);
-        try!(handle.write_interrupt(endpoint_out.unwrap(), &[0x13], timeout));
+        // Tell the adapter to start sending packets. Read-only clones without an output endpoint
+        // send packets unprompted.
+        let endpoint_out = endpoints.endpoint_out;
+        #[cfg(feature = "tracing")]
+        {
+            span.record("interface", &interface_number);
+            span.record("endpoint_in", &endpoints.endpoint_in);
+            if let Some(endpoint_out) = endpoint_out {
+                span.record("endpoint_out", &endpoint_out);
+            }
+        }
+
+        if let Some(endpoint_out) = endpoint_out {
+            let timeout = Duration::from_secs(1);
+            try!(handle.write_interrupt(endpoint_out, &[0x13], timeout));
+        }
 
         Ok(Listener {
             handle: handle,
-            buffer: [0; 37],
+            buffer: [0; PACKET_LEN],
             has_kernel_driver: has_kernel_driver,
             interface: interface_number,
-            endpoint_in: endpoint_in.unwrap(),
+            endpoint_in: endpoints.endpoint_in,
+            #[cfg(feature = "rumble")]
+            endpoint_out: endpoint_out,
+            read_only: endpoint_out.is_none(),
+            ambiguous_endpoints: endpoints.ambiguous,
+            #[cfg(feature = "rumble")]
+            rumble: None,
+            #[cfg(feature = "rumble")]
+            rumble_refresh: None,
+            origins: [None; 4],
+            last_frame: None,
+            pair: FramePair { previous: [None; 4], current: [None; 4] },
+            last_read: None,
+            last_interval: None,
+            log: PacketLog::default(),
+            raw_capture: None,
+            trigger_click_threshold: None,
+            invert_triggers: false,
+            strict_kind: false,
+            reattach_on_drop: true,
+            packet_len: PacketLen::Fixed(PACKET_LEN),
+            scratch: Vec::new(),
+            max_packet_size: endpoints.max_packet_size,
+            bcd_device: bcd_device,
+        })
+    }
+
+    /// Calls `listen` up to `attempts` times, sleeping for `delay` between attempts, for opening an
+    /// adapter that was just plugged in.
+    ///
+    /// Right after an adapter arrives, the operating system may not have finished setting it up,
+    /// so opening it can fail transiently. Only `Error::Usb(libusb::Error::NoDevice)` and
+    /// `Error::DeviceBusy` are retried. Any other error is returned immediately, as is the last
+    /// error once all attempts have failed. At least one attempt is made, even if `attempts` is
+    /// `0`.
+    pub fn listen_with_retry(&mut self, attempts: usize, delay: Duration)
+        -> Result<Listener<'a>, Error>
+    {
+        let mut attempt = 1;
+        loop {
+            match self.listen() {
+                Err(Error::Usb(libusb::Error::NoDevice)) |
+                Err(Error::DeviceBusy) if attempt < attempts => {
+                    attempt += 1;
+                    thread::sleep(delay);
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Forces `listen` to use the interface with the given number, or restores auto-detection if
+    /// `None` is passed (the default).
+    ///
+    /// This is an escape hatch for clones whose controller data interface is not the one that
+    /// auto-detection picks. The endpoints are still located within the forced interface, and
+    /// `listen` fails with `Error::UnrecognizedProtocol` if the interface does not exist or has no
+    /// input endpoint. `debug_descriptors` lists the interfaces that an adapter exposes.
+    pub fn set_interface_number(&mut self, number: Option<u8>) {
+        self.interface_number = number;
+    }
+
+    /// Returns the adapter's device release number (`bcdDevice`), in binary-coded decimal, which
+    /// identifies its firmware revision.
+    ///
+    /// This is read from the device descriptor, so the device does not need to be opened.
+    pub fn firmware_version(&mut self) -> Result<u16, Error> {
+        Ok(bcd_device(&try!(self.device.device_descriptor())))
+    }
+
+    /// Returns the adapter's firmware revision formatted as `major.minor`, such as `"1.03"`.
+    pub fn firmware_version_string(&mut self) -> Result<String, Error> {
+        Ok(format_bcd(try!(self.firmware_version())))
+    }
+
+    /// Returns what is known about the adapter's capabilities.
+    ///
+    /// The descriptors are read without opening the device, so this works even while a
+    /// `Listener` is alive. Not every field is equally reliable:
+    ///
+    /// * `port_count` is authoritative: the protocol always reports exactly four ports.
+    /// * `rumble_supported` is inferred from whether the interface that `listen` would use has an
+    ///   output endpoint, and is always `false` if the `rumble` feature is disabled. An adapter
+    ///   that accepts rumble commands may still not power the motors, as the official adapter
+    ///   does when its second (grey) USB plug is not connected; see `Listener::external_power`.
+    /// * `variant` is always `AdapterVariant::Unknown` for now; see `AdapterVariant`.
+    ///
+    /// As with `listen`, `Error::UnrecognizedProtocol` is returned if no interface with an input
+    /// endpoint is found.
+    pub fn capabilities(&mut self) -> Result<AdapterCapabilities, Error> {
+        let config = try!(self.device.config_descriptor(0));
+        let endpoints = match find_endpoints(&config, self.interface_number) {
+            Some(endpoints) => endpoints,
+            None => return Err(Error::UnrecognizedProtocol),
+        };
+
+        Ok(AdapterCapabilities {
+            rumble_supported: cfg!(feature = "rumble") && endpoints.endpoint_out.is_some(),
+            port_count: Port::ALL.len(),
+            variant: AdapterVariant::Unknown,
         })
     }
+
+    /// Performs a USB port reset of the adapter, which often recovers a wedged adapter without
+    /// unplugging it.
+    ///
+    /// The device is re-enumerated by the operating system, so this `Adapter` and any `Listener`
+    /// created from it should be considered invalid afterwards: drop them, then find the adapter
+    /// again through `Scanner::find_adapter` and call `listen`. On some platforms the reset
+    /// invalidates open handles outright, causing any surviving `Listener` to fail its reads.
+    pub fn reset(&mut self) -> Result<(), Error> {
+        let mut handle = try!(self.device.open().map_err(busy_error));
+        match handle.reset() {
+            // `libusb` reports a device that re-enumerated as a different device as not found,
+            // which is the expected outcome here.
+            Ok(()) | Err(libusb::Error::NotFound) => Ok(()),
+            Err(err) => Err(Error::Usb(err)),
+        }
+    }
+
+    /// Returns a human-readable dump of the adapter's configurations, interfaces, and endpoints,
+    /// including endpoint addresses, directions, and transfer types.
+    ///
+    /// This is meant for troubleshooting, such as triaging a clone adapter that `listen` rejects
+    /// with `Error::UnrecognizedProtocol`, and its format is not stable. Descriptors are read
+    /// without opening the device, so this works even while a `Listener` is alive. An error is
+    /// only returned if the device descriptor cannot be read; a configuration that fails to read
+    /// is noted in the dump in place of its contents.
+    pub fn debug_descriptors(&mut self) -> Result<String, Error> {
+        let device_desc = try!(self.device.device_descriptor());
+        let mut dump = format!("device {:04x}:{:04x}, release {}, {} configuration(s)\n",
+                               device_desc.vendor_id(), device_desc.product_id(),
+                               format_bcd(bcd_device(&device_desc)),
+                               device_desc.num_configurations());
+
+        for index in 0..device_desc.num_configurations() {
+            let config = match self.device.config_descriptor(index) {
+                Ok(config) => config,
+                Err(err) => {
+                    dump.push_str(&format!("  configuration #{}: error: {}\n", index, err));
+                    continue;
+                },
+            };
+
+            dump.push_str(&format!("  configuration {}: {} interface(s)\n", config.number(),
+                                   config.num_interfaces()));
+            for interface in config.interfaces() {
+                for desc in interface.descriptors() {
+                    dump.push_str(&format!(
+                        "    interface {}, setting {}: class {:#04x}, {} endpoint(s)\n",
+                        desc.interface_number(), desc.setting_number(), desc.class_code(),
+                        desc.num_endpoints()));
+                    for endpoint in desc.endpoint_descriptors() {
+                        dump.push_str(&format!(
+                            "      endpoint {:#04x}: {:?} {:?}, max packet size {}\n",
+                            endpoint.address(), endpoint.direction(), endpoint.transfer_type(),
+                            endpoint.max_packet_size()));
+                    }
+                }
+            }
+        }
+
+        Ok(dump)
+    }
 }
 
 /// An interface that reads packets of controller data on each iteration.
 ///
 /// This interface owns an opened handle to the USB device that is closed once the `Listener`
 /// instance is dropped.
+///
+/// Reads and rumble commands use separate endpoints, both captured by `Adapter::listen`, so calls
+/// to `read` and `rumble` can be freely interleaved on a single `Listener`.
 pub struct Listener<'a> {
     handle: DeviceHandle<'a>,
-    buffer: [u8; 37],
+    buffer: [u8; PACKET_LEN],
     has_kernel_driver: bool,
     interface: u8,
     endpoint_in: u8,
+    #[cfg(feature = "rumble")]
+    endpoint_out: Option<u8>,
+    read_only: bool,
+    ambiguous_endpoints: bool,
+    #[cfg(feature = "rumble")]
+    rumble: Option<([u8; 4], Instant)>,
+    #[cfg(feature = "rumble")]
+    rumble_refresh: Option<Duration>,
+    origins: [Option<Controller>; 4],
+    last_frame: Option<[Option<Controller>; 4]>,
+    pair: FramePair,
+    last_read: Option<Instant>,
+    last_interval: Option<Duration>,
+    log: PacketLog,
+    raw_capture: Option<Box<Write + Send>>,
+    trigger_click_threshold: Option<u8>,
+    invert_triggers: bool,
+    strict_kind: bool,
+    reattach_on_drop: bool,
+    packet_len: PacketLen,
+    scratch: Vec<u8>,
+    max_packet_size: u16,
+    bcd_device: u16,
 }
 
 impl<'a> Listener<'a> {
@@ -156,132 +684,2116 @@ impl<'a> Listener<'a> {
     ///
     /// * The USB device becomes disconnected
     /// * The USB driver throws an error, fatal or not
-    /// * A USB message was successfully read, but it was not the right size
+    /// * A USB message was successfully read, but it was not the right size (including a
+    ///   zero-length read), reported as `Error::InvalidPacket` along with the observed length
+    /// * A USB message was successfully read, but it was not a controller data report, reported as
+    ///   `Error::UnexpectedReportId`
     ///
     /// It is wise to treat all errors returned as fatal, and to reestablish the adapter connection
     /// through `Scanner::find_adapter`.
+    ///
+    /// The returned states are adjusted by, in order, the trigger inversion configured through
+    /// `set_invert_triggers`, any origins captured through `capture_origin`, and the trigger
+    /// clicks configured through `set_synthesize_trigger_click`.
     pub fn read(&mut self) -> Result<[Option<Controller>; 4], Error> {
-        let timeout = Duration::from_secs(1);
-        match self.handle.read_interrupt(self.endpoint_in, &mut self.buffer, timeout) {
-            Ok(read) if read == 37 => Ok(Controller::parse_packet(&self.buffer)),
-            Ok(_) => Err(Error::InvalidPacket),
-            Err(err) => Err(Error::Usb(err)),
+        let controllers = try!(self.read_packet(Duration::from_secs(1)));
+        Ok(self.finish_read(controllers))
+    }
+
+    /// Discards any packets that are immediately available and returns the states from the most
+    /// recent one.
+    ///
+    /// This guarantees the freshest available frame at the cost of dropping intermediate frames,
+    /// which minimizes input lag for applications that read less often than the adapter sends
+    /// packets. A packet is first read as with `read`, after which packets are read with a very
+    /// short timeout until none is available (or until a small number of packets have been
+    /// skipped, in case the adapter sends packets faster than they can be drained).
+    ///
+    /// Only the first read can fail, with the same errors as `read`. If a later read fails, for
+    /// example because a stray report is rejected with `Error::UnexpectedReportId`, draining stops
+    /// and the states from the freshest packet read so far are returned. This never fails where
+    /// `read` would have succeeded.
+    pub fn read_latest(&mut self) -> Result<[Option<Controller>; 4], Error> {
+        let mut controllers = try!(self.read_packet(Duration::from_secs(1)));
+
+        for _ in 0..MAX_DRAINED_PACKETS {
+            match self.read_packet(Duration::from_millis(1)) {
+                Ok(latest) => controllers = latest,
+                // Usually a timeout, meaning that no packet is left to skip. Any other error is not
+                // worth discarding the packet that was already read.
+                Err(_) => break,
+            }
         }
+
+        Ok(self.finish_read(controllers))
     }
-}
 
-impl<'a> Drop for Listener<'a> {
-    fn drop(&mut self) {
-        if self.has_kernel_driver {
-            let _ = self.handle.attach_kernel_driver(self.interface);
+    /// Reads a data packet like `read` and returns the states from both this read and the one
+    /// before it.
+    ///
+    /// The pair is updated by every successful read, whichever method performs it, including
+    /// `capture_origin` and each packet read by `measure_round_trip`. `previous` is therefore
+    /// always the `current` of the read before, and no successful read falls between the two
+    /// frames. Packets skipped by `read_latest` are not reads of their own and never appear in the
+    /// pair. Failed reads leave the pair untouched.
+    pub fn read_pair(&mut self) -> Result<&FramePair, Error> {
+        try!(self.read());
+        Ok(&self.pair)
+    }
+
+    /// Reads a data packet like `read`, pairing the states with the moment the read completed.
+    pub fn read_timed(&mut self) -> Result<TimedFrame, Error> {
+        let controllers = try!(self.read());
+        Ok(TimedFrame {
+            at: Instant::now(),
+            controllers: controllers,
+        })
+    }
+
+    /// Measures the mean time between consecutive successful reads over `samples` intervals,
+    /// characterizing how often the adapter delivers packets.
+    ///
+    /// Despite its name, this does not measure a true round trip: rumble commands are not
+    /// reflected in the input data, so there is no way to observe when a command takes effect.
+    /// Instead, `samples + 1` packets are read back to back and the intervals between the
+    /// completion of each read are averaged. This read-to-read latency is a proxy for the
+    /// adapter's polling latency. The packets read are not returned, but they are recorded like
+    /// those of any other read (see `last_frame` and `read_pair`).
+    ///
+    /// Any read error aborts the measurement. A `Duration` of zero is returned if `samples` is `0`.
+    pub fn measure_round_trip(&mut self, samples: usize) -> Result<Duration, Error> {
+        if samples == 0 {
+            return Ok(Duration::from_secs(0));
+        }
+
+        try!(self.read());
+        let start = Instant::now();
+        for _ in 0..samples {
+            try!(self.read());
         }
+
+        Ok(start.elapsed() / samples as u32)
     }
-}
 
-/// The state of a GameCube controller at a given moment in time.
-///
-/// Note that the hardware will likely never report either extreme of the spectrum for any of the
-/// analog inputs. For example, all `u8` fields may report only within the range of `30` to `225`.
-/// Also, the hardware will likely never report a perfect `127` for the resting position of any of
-/// the joystick axes. Keep in my that this library does not do any analog dead zone correction.
-#[derive(Clone, Copy, Debug)]
-pub struct Controller {
-    /// The classification of this controller.
-    pub kind: ControllerKind,
-    /// "A" button status.
-    pub a: bool,
-    /// "B" button status.
-    pub b: bool,
-    /// "X" button status.
-    pub x: bool,
-    /// "Y" button status.
-    pub y: bool,
-    /// Directional pad up button status.
-    pub up: bool,
-    /// Directional pad down button status.
-    pub down: bool,
-    /// Directional pad left button status.
-    pub left: bool,
-    /// Directional pad right button status.
-    pub right: bool,
-    /// Digital "L" button (full depression) status.
-    pub l: bool,
-    /// Digital "R" button (full depression) status.
-    pub r: bool,
-    /// The level of depression of the analog "L" button, `0` being completely up, `255` being
-    /// completely pressed in.
-    pub l_analog: u8,
-    /// The level of depression of the analog "R" button, `0` being completely up, `255` being
-    /// completely pressed in.
-    pub r_analog: u8,
-    /// "Z" button status.
-    pub z: bool,
-    /// Start button status.
-    pub start: bool,
-    /// The x-axis position of the primary analog joystick, `0` being completely left, `255` being
-    /// completely right.
-    pub stick_x: u8,
-    /// The y-axis position of the primary analog joystick, `0` being completely down, `255` being
-    /// completely up.
-    pub stick_y: u8,
-    /// The x-axis position of the secondary ("C") analog joystick, `0` being completely left,
-    /// `255` being completely right.
-    pub c_stick_x: u8,
-    /// The y-axis position of the secondary ("C") analog joystick, `0` being completely down,
-    /// `255` being completely up.
-    pub c_stick_y: u8,
-}
+    /// Reads a data packet like `read`, additionally returning a copy of the raw packet the states
+    /// were decoded from.
+    ///
+    /// The states include the listener's adjustments (see `read`), while the raw bytes are as
+    /// received from the adapter. Only the 37 bytes of the controller data packet are returned:
+    /// if a longer message was accepted through `set_packet_len`, its padding is cut off. Use
+    /// `set_raw_capture` to record messages in full.
+    pub fn read_raw_and_parsed(&mut self)
+        -> Result<([u8; 37], [Option<Controller>; 4]), Error>
+    {
+        let controllers = try!(self.read());
+        Ok((self.buffer, controllers))
+    }
 
-impl Controller {
-    // # Panics
-    //
-    // Panics if `data` is not at least 9 bytes.
-    fn parse(data: &[u8]) -> Option<Controller> {
-        let kind = match data[0] >> 4 {
-            0 => return None,
-            1 => ControllerKind::Wired,
-            2 => ControllerKind::Wireless,
-            _ => ControllerKind::Unknown,
+    /// Reads a data packet like `read`, additionally returning `true` if the packet is
+    /// byte-for-byte identical to the previously read packet.
+    ///
+    /// The adapter often repeats the same state, so this lets consumers cheaply skip redundant
+    /// processing. The first packet read by a `Listener` is never considered a repeat, and
+    /// packets that were rejected are left out of the comparison.
+    pub fn read_with_repeat_flag(&mut self) -> Result<([Option<Controller>; 4], bool), Error> {
+        let controllers = try!(self.read());
+        Ok((controllers, self.log.repeated))
+    }
+
+    // Reads and parses a data packet without applying any of the listener's adjustments.
+    fn read_packet(&mut self, timeout: Duration) -> Result<[Option<Controller>; 4], Error> {
+        // Packets of the default length are read straight into `buffer`. Any other length needs
+        // the larger scratch buffer sized by `set_packet_len`.
+        let direct = self.packet_len == PacketLen::Fixed(PACKET_LEN);
+        let read = {
+            let data: &mut [u8] = if direct { &mut self.buffer } else { &mut self.scratch };
+            try!(read_transfer(&mut self.handle, self.endpoint_in, timeout, data))
         };
 
-        Some(Controller {
-            kind: kind,
-            a: data[1] & (1 << 0) != 0,
-            b: data[1] & (1 << 1) != 0,
-            x: data[1] & (1 << 2) != 0,
-            y: data[1] & (1 << 3) != 0,
-            left: data[1] & (1 << 4) != 0,
-            right: data[1] & (1 << 5) != 0,
-            down: data[1] & (1 << 6) != 0,
-            up: data[1] & (1 << 7) != 0,
-            start: data[2] & (1 << 0) != 0,
-            z: data[2] & (1 << 1) != 0,
-            r: data[2] & (1 << 2) != 0,
-            l: data[2] & (1 << 3) != 0,
-            stick_x: data[3],
-            stick_y: data[4],
-            c_stick_x: data[5],
-            c_stick_y: data[6],
-            l_analog: data[7],
-            r_analog: data[8],
-        })
+        let data: &[u8] = if direct { &self.buffer } else { &self.scratch };
+        if let Some(ref mut capture) = self.raw_capture {
+            // Capturing is best-effort and must not interrupt reading.
+            let _ = write_capture_record(&mut **capture, &data[..read]);
+        }
+
+        self.buffer = try!(self.log.accept(self.packet_len, self.strict_kind, read, data));
+
+        // Trigger inversion is part of decoding, rather than of `adjust`, so that origins captured
+        // through `capture_origin` are inverted too.
+        let mut controllers = Controller::parse_packet(&self.buffer);
+        if self.invert_triggers {
+            invert_triggers(&mut controllers);
+        }
+
+        Ok(controllers)
     }
 
-    // # Panics
-    //
-    // Panics if `data` is not at least 37 bytes.
-    fn parse_packet(data: &[u8]) -> [Option<Controller>; 4] {
-        [
-            Controller::parse(&data[1..10]),
-            Controller::parse(&data[10..19]),
-            Controller::parse(&data[19..28]),
-            Controller::parse(&data[28..37])
-        ]
+    // Adjusts freshly parsed states and records them, along with the time of the read, as the
+    // outcome of a successful read.
+    fn finish_read(&mut self, controllers: [Option<Controller>; 4]) -> [Option<Controller>; 4] {
+        let controllers = self.adjust(controllers);
+        let now = Instant::now();
+        self.last_interval = self.last_read.map(|last| now.duration_since(last));
+        self.last_read = Some(now);
+        self.last_frame = Some(controllers);
+        self.pair.push(controllers);
+        controllers
     }
-}
 
-/// The classification of a GameCube controller.
+    // Applies the configured origins and trigger click synthesis to freshly read states, whose
+    // triggers have already been inverted if configured.
+    fn adjust(&self, mut controllers: [Option<Controller>; 4]) -> [Option<Controller>; 4] {
+        for (controller, origin) in controllers.iter_mut().zip(self.origins.iter()) {
+            if let (&mut Some(ref mut controller), &Some(ref origin)) = (controller, origin) {
+                for &axis in Axis::ALL.iter() {
+                    let offset = origin.axis(axis) as i16 - axis.neutral() as i16;
+                    let value = controller.axis(axis) as i16 - offset;
+                    *controller.axis_mut(axis) = clamp_u8(value);
+                }
+            }
+        }
+
+        synthesize_trigger_clicks(&mut controllers, self.trigger_click_threshold);
+        controllers
+    }
+
+    /// Reads a data packet and records the joystick and trigger values of the controller on
+    /// `port` as its origin, emulating the calibration a GameCube performs when it is powered on.
+    ///
+    /// Subsequent reads shift that port's analog values by the distance between the origin and
+    /// the neutral position (`127` for joystick axes, `0` for triggers), clamping to the valid
+    /// range. The controller should be left untouched while its origin is captured.
+    ///
+    /// This only corrects the neutral offset of the inputs; it does not calibrate the range of
+    /// motion, so an offset stick may reach one extreme before the other.
+    ///
+    /// `Error::PortEmpty` is returned if no controller is connected to `port`. Either way, the
+    /// packet counts as a successful read, updating `last_frame` and the pair of `read_pair`.
+    pub fn capture_origin(&mut self, port: Port) -> Result<(), Error> {
+        let controllers = try!(self.read_packet(Duration::from_secs(1)));
+        let captured = controllers[port.index()];
+        if captured.is_some() {
+            self.origins[port.index()] = captured;
+        }
+
+        // The packet is recorded like any other read, with the new origin already applied.
+        self.finish_read(controllers);
+        match captured {
+            Some(_) => Ok(()),
+            None => Err(Error::PortEmpty(port)),
+        }
+    }
+
+    /// Forgets the origin captured for `port`, if any, so that its values are reported as-is.
+    pub fn clear_origin(&mut self, port: Port) {
+        self.origins[port.index()] = None;
+    }
+
+    /// Makes `read` report the digital "L" or "R" button as held whenever its analog value
+    /// exceeds `threshold`, even if the hardware does not report the click.
+    ///
+    /// Worn controllers sometimes stop reporting the digital click at full depression. Passing
+    /// `None` (the default) disables this behavior.
+    pub fn set_synthesize_trigger_click(&mut self, threshold: Option<u8>) {
+        self.trigger_click_threshold = threshold;
+    }
+
+    /// Starts or stops the rumble motor of the controller connected to each port.
+    ///
+    /// This is a convenience wrapper around `rumble_raw`, where `true` is sent as `1` and `false`
+    /// as `0`. The adapter keeps rumbling until it is told otherwise, so any rumbling ports are
+    /// stopped when the `Listener` is dropped.
+    #[cfg(feature = "rumble")]
+    pub fn rumble(&mut self, ports: [bool; 4]) -> Result<(), Error> {
+        self.rumble_raw([ports[0] as u8, ports[1] as u8, ports[2] as u8, ports[3] as u8])
+    }
+
+    /// Sends a rumble command with a raw intensity byte for each port.
+    ///
+    /// As far as is currently known, the adapter treats rumble as binary: `0` stops the motor and
+    /// any other value starts it at full strength. This method exists to allow experimenting with
+    /// the protocol should finer control be discovered.
+    ///
+    /// Wireless controllers do not rumble, and the official adapter only rumbles when its second
+    /// (grey) USB plug is connected. Neither condition results in an error. However,
+    /// `Error::RumbleUnsupported` is returned if the listener is read-only (see `is_read_only`).
+    ///
+    /// To avoid flooding the adapter when called on every frame, a command is only written if it
+    /// differs from the last one sent, or if the interval set through `set_rumble_refresh` has
+    /// elapsed since then.
+    #[cfg(feature = "rumble")]
+    pub fn rumble_raw(&mut self, intensities: [u8; 4]) -> Result<(), Error> {
+        let endpoint_out = match self.endpoint_out {
+            Some(endpoint_out) => endpoint_out,
+            None => return Err(Error::RumbleUnsupported),
+        };
+
+        if !should_send_rumble(intensities, self.rumble, self.rumble_refresh, Instant::now()) {
+            return Ok(());
+        }
+
+        let timeout = Duration::from_secs(1);
+        let message = [0x11, intensities[0], intensities[1], intensities[2], intensities[3]];
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("rumble", endpoint = endpoint_out,
+                                        intensities = ?intensities,
+                                        elapsed_us = tracing::field::Empty);
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+        #[cfg(feature = "tracing")]
+        let start = Instant::now();
+
+        let result = self.handle.write_interrupt(endpoint_out, &message, timeout);
+        #[cfg(feature = "tracing")]
+        span.record("elapsed_us", &(start.elapsed().as_micros() as u64));
+        try!(result);
+        self.rumble = Some((intensities, Instant::now()));
+        Ok(())
+    }
+
+    /// Sets how often `rumble` and `rumble_raw` resend an unchanged command, for adapters that
+    /// stop rumbling on their own after a while.
+    ///
+    /// The default is `None`, meaning an unchanged command is never resent, which suits the
+    /// official adapter: it keeps rumbling until told otherwise.
+    #[cfg(feature = "rumble")]
+    pub fn set_rumble_refresh(&mut self, interval: Option<Duration>) {
+        self.rumble_refresh = interval;
+    }
+
+    /// Makes `read` report each analog trigger value as `255 - value`.
+    ///
+    /// A few clone adapters report the triggers inverted, as `255` at rest and `0` when fully
+    /// pressed. This is disabled by default.
+    pub fn set_invert_triggers(&mut self, invert: bool) {
+        self.invert_triggers = invert;
+    }
+
+    /// Makes reads fail with `Error::UnknownControllerKind` when a port reports a controller
+    /// classification this library does not recognize, instead of reporting
+    /// `ControllerKind::Unknown`.
+    ///
+    /// This is disabled by default. Enabling it surfaces protocol mismatches early for
+    /// applications that would rather fail than guess.
+    pub fn set_strict_kind(&mut self, strict: bool) {
+        self.strict_kind = strict;
+    }
+
+    /// Controls whether dropping the `Listener` reattaches the kernel driver that `listen`
+    /// detached from the adapter. This has no effect if no driver was attached to begin with.
+    ///
+    /// This is enabled by default. Disabling it avoids detaching and reattaching the driver on
+    /// every cycle for applications that repeatedly open and close the adapter. The next call to
+    /// `listen` then finds no driver to detach. However, the driver stays detached after the
+    /// `Listener` is dropped, so other software that relies on it (such as the Linux kernel's own
+    /// GameCube adapter support) cannot use the adapter until it is reattached or reconnected.
+    pub fn set_reattach_on_drop(&mut self, reattach: bool) {
+        self.reattach_on_drop = reattach;
+    }
+
+    /// Sets the length that messages read from the adapter must have. The default is
+    /// `PacketLen::Fixed(37)`, the length of a controller data packet.
+    ///
+    /// Some clones pad their packets, for example to the maximum packet size of their endpoint.
+    /// With `PacketLen::Auto`, any message of at least 37 bytes is accepted. Either way, only the
+    /// first 37 bytes are parsed, and messages of any other length are rejected with
+    /// `Error::InvalidPacket`.
+    pub fn set_packet_len(&mut self, packet_len: PacketLen) {
+        let capacity = match packet_len {
+            PacketLen::Fixed(len) => len,
+            PacketLen::Auto => self.max_packet_size as usize,
+        };
+        self.packet_len = packet_len;
+        self.scratch = vec![0; cmp::max(capacity, PACKET_LEN)];
+    }
+
+    /// Starts or stops appending every message read from the adapter to `writer`, to make bug
+    /// reports about unusual adapters reproducible. Passing `None` (the default) stops capturing.
+    ///
+    /// Every message is captured as it arrives, before it is validated, so rejected messages are
+    /// included. Each is appended as a record made of:
+    ///
+    /// 1. The time of the read, in microseconds since the Unix epoch, as a little-endian `u64`
+    /// 2. The length of the message, as a little-endian `u16`
+    /// 3. The message itself, usually 37 bytes starting with the report ID (`0x21`)
+    ///
+    /// A valid message can be decoded again with `Controller::iter_packet`. Errors returned by
+    /// `writer` are ignored so that capturing never interrupts reading. Wrap slow writers, such as
+    /// files, in a `std::io::BufWriter`; it is flushed once the `Listener` is dropped.
+    pub fn set_raw_capture(&mut self, writer: Option<Box<Write + Send>>) {
+        self.raw_capture = writer;
+    }
+
+    /// Returns whether the adapter reports having external power, as observed in the most recently
+    /// read packet, or `None` if no packet has been read yet.
+    ///
+    /// The official adapter only powers rumble when its second (grey) USB plug is connected. It
+    /// signals this through bit 2 (`0x04`) of each port's status byte, the same byte whose upper
+    /// nibble holds the controller classification. This has been established by reverse
+    /// engineering rather than documentation, and clones may never set the bit even when they are
+    /// able to rumble.
+    pub fn external_power(&self) -> Option<bool> {
+        self.log.latest.map(|packet| {
+            Port::ALL.iter().any(|port| packet[1 + port.index() * 9] & 0x04 != 0)
+        })
+    }
+
+    /// Briefly rumbles every wired controller seen in the most recently read packet and returns
+    /// whether the adapter accepted the command, as a weak fallback for `external_power`.
+    ///
+    /// Rumble is not reflected in the input data, so this cannot confirm that any motor actually
+    /// turned. It only reports whether the rumble command was written without a USB error. Some
+    /// adapters reject or stall rumble commands without external power, in which case `false` is
+    /// returned, but others accept them regardless. Only the errors such an adapter produces,
+    /// `Usb(Pipe)`, `Usb(Timeout)`, and `Usb(Io)`, count as a rejection. Any other error, such as
+    /// `Usb(NoDevice)` after a disconnect, is returned.
+    ///
+    /// If the adapter is read-only, `Error::RumbleUnsupported` is returned. Otherwise, `false` is
+    /// also returned if no wired controller is connected, as there is nothing to pulse.
+    /// Afterwards, the rumble state from before the probe is restored.
+    #[cfg(feature = "rumble")]
+    pub fn probe_rumble_power(&mut self) -> Result<bool, Error> {
+        if self.endpoint_out.is_none() {
+            return Err(Error::RumbleUnsupported);
+        }
+
+        let mut pulse = [0; 4];
+        if let Some(controllers) = self.last_frame {
+            for (intensity, controller) in pulse.iter_mut().zip(controllers.iter()) {
+                if controller.map_or(false, |c| c.kind == ControllerKind::Wired) {
+                    *intensity = 1;
+                }
+            }
+        }
+
+        if pulse == [0; 4] {
+            return Ok(false);
+        }
+
+        let cached = self.rumble;
+        // Forget the last command so that the pulse is sent even if it is already in effect.
+        self.rumble = None;
+        match self.rumble_raw(pulse) {
+            Ok(()) => {},
+            Err(err) => {
+                // The pulse was not sent, so the last command is still in effect. Keeping track of
+                // it matters for stopping rumble once the `Listener` is dropped.
+                self.rumble = cached;
+                return match err {
+                    Error::Usb(libusb::Error::Pipe) |
+                    Error::Usb(libusb::Error::Timeout) |
+                    Error::Usb(libusb::Error::Io) => Ok(false),
+                    err => Err(err),
+                };
+            },
+        }
+
+        // A short pulse that is still long enough for the adapter to act on.
+        thread::sleep(Duration::from_millis(20));
+        try!(self.rumble_raw(cached.map_or([0; 4], |(sent, _)| sent)));
+        Ok(true)
+    }
+
+    /// Returns the underlying `libusb` device handle, for issuing transfers this library does not
+    /// wrap.
+    ///
+    /// This is an escape hatch that is only available with the `unstable-handle` feature, and it
+    /// is unsupported for normal use. It exposes an implementation detail that may change.
+    #[cfg(feature = "unstable-handle")]
+    pub fn handle(&self) -> &DeviceHandle<'a> {
+        &self.handle
+    }
+
+    /// Returns the underlying `libusb` device handle mutably. See `handle`.
+    ///
+    /// Transfers issued through the handle can desynchronize the state the `Listener` keeps
+    /// about the adapter. For example, a rumble command sent directly is not known to the
+    /// `Listener`, which will then not stop it when dropped.
+    #[cfg(feature = "unstable-handle")]
+    pub fn handle_mut(&mut self) -> &mut DeviceHandle<'a> {
+        &mut self.handle
+    }
+
+    /// Returns the states from the most recent successful `read` (or any other method that reads
+    /// a packet, such as `capture_origin`), or `None` if nothing has been read yet.
+    ///
+    /// Failed reads leave this untouched, so an application can keep displaying the previous
+    /// state through a transient error. A new `Listener`, such as one created after reconnecting,
+    /// starts without a frame.
+    pub fn last_frame(&self) -> Option<[Option<Controller>; 4]> {
+        self.last_frame
+    }
+
+    /// Returns the total number of bytes of all packets successfully read by this `Listener`,
+    /// including any padding accepted through `set_packet_len`. Packets that were rejected, for
+    /// any reason including `set_strict_kind`, are not counted.
+    ///
+    /// Combined with timing, such as `last_interval`, this gives the effective bandwidth of the
+    /// connection. Packets skipped by `read_latest` are counted, as they were transferred all the
+    /// same.
+    pub fn bytes_read(&self) -> u64 {
+        self.log.bytes_read
+    }
+
+    /// Returns the time between the two most recent successful reads, or `None` until two reads
+    /// have succeeded.
+    ///
+    /// This reflects the actual timing of packet delivery, which makes it suitable as a per-frame
+    /// delta time for loops that are paced by `read`. Failed reads are not counted, so an error
+    /// between two reads lengthens the reported interval.
+    pub fn last_interval(&self) -> Option<Duration> {
+        self.last_interval
+    }
+
+    /// Returns `true` if the adapter has no output endpoint, as is the case for some read-only
+    /// clones. Such adapters can be read from, but cannot rumble.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns the address of the endpoint that controller data is read from.
+    pub fn endpoint_in(&self) -> u8 {
+        self.endpoint_in
+    }
+
+    /// Returns the address of the endpoint that commands are written to, or `None` if the adapter
+    /// is read-only.
+    ///
+    /// This is only available with the `rumble` feature, as the address is not kept otherwise.
+    #[cfg(feature = "rumble")]
+    pub fn endpoint_out(&self) -> Option<u8> {
+        self.endpoint_out
+    }
+
+    /// Returns `true` if the adapter's active interface setting has more than one input or more
+    /// than one output endpoint.
+    ///
+    /// Genuine adapters have exactly one of each. Otherwise, the first interrupt endpoint in each
+    /// direction was chosen (or the first endpoint if none is an interrupt endpoint), which may
+    /// not be the one that the adapter actually uses. This is worth logging when diagnosing an
+    /// unusual adapter.
+    pub fn has_ambiguous_endpoints(&self) -> bool {
+        self.ambiguous_endpoints
+    }
+
+    /// Returns the maximum packet size of the adapter's input endpoint.
+    ///
+    /// This reflects the value advertised by the endpoint descriptor, not the number of bytes
+    /// actually read per transfer. Some adapters advertise more than the 37 bytes that make up a
+    /// controller data packet.
+    pub fn max_packet_size(&self) -> u16 {
+        self.max_packet_size
+    }
+
+    /// Returns the SDL joystick GUID of the adapter, as computed by `adapter_sdl_guid`.
+    ///
+    /// The device release number is read from the device descriptor when the `Listener` is
+    /// created.
+    pub fn sdl_guid(&self) -> String {
+        adapter_sdl_guid(self.bcd_device)
+    }
+}
+
+impl<'a> Drop for Listener<'a> {
+    fn drop(&mut self) {
+        #[cfg(feature = "rumble")]
+        {
+            if self.rumble.map_or(false, |(sent, _)| sent != [0; 4]) {
+                let _ = self.rumble_raw([0; 4]);
+            }
+        }
+
+        if self.has_kernel_driver && self.reattach_on_drop {
+            let _ = self.handle.attach_kernel_driver(self.interface);
+        }
+    }
+}
+
+/// Reads a single data packet from an adapter's input endpoint and returns the states for each of
+/// the four possibly connected controllers.
+///
+/// This performs the same transfer, validation, and parsing as `Listener::read` does with the
+/// default packet length, without any of the listener's state or adjustments, for use with a
+/// device handle obtained by other means. The adapter must already have been told to
+/// start sending packets. The following failures are reported:
+///
+/// * `Error::Usb` if the transfer fails, including when `timeout` elapses
+/// * `Error::InvalidPacket` if the message is not exactly 37 bytes long
+/// * `Error::UnexpectedReportId` if the message does not start with the controller data report
+///   ID (`0x21`)
+pub fn read_frame(handle: &mut DeviceHandle, endpoint: u8, timeout: Duration)
+    -> Result<[Option<Controller>; 4], Error>
+{
+    let mut buffer = [0; PACKET_LEN];
+    let read = try!(read_transfer(handle, endpoint, timeout, &mut buffer));
+    try!(validate_packet(PacketLen::default(), read, &buffer));
+    Ok(Controller::parse_packet(&buffer))
+}
+
+// Checks that a message of `read` bytes, received into `data`, is a controller data packet: its
+// length must be accepted by `packet_len` and it must start with the report ID. Only then is it
+// safe to parse the first 37 bytes of `data`.
+fn validate_packet(packet_len: PacketLen, read: usize, data: &[u8]) -> Result<(), Error> {
+    let valid = match packet_len {
+        PacketLen::Fixed(len) => read == len && len >= PACKET_LEN,
+        PacketLen::Auto => read >= PACKET_LEN,
+    };
+
+    if !valid || data.len() < read {
+        Err(Error::InvalidPacket { read: read })
+    } else if data[0] != REPORT_ID {
+        Err(Error::UnexpectedReportId(data[0]))
+    } else {
+        Ok(())
+    }
+}
+
+// Reports each analog trigger value of every connected controller as `255 - value`. See
+// `Listener::set_invert_triggers`.
+fn invert_triggers(controllers: &mut [Option<Controller>; 4]) {
+    for controller in controllers.iter_mut() {
+        if let Some(ref mut controller) = *controller {
+            controller.l_analog = 255 - controller.l_analog;
+            controller.r_analog = 255 - controller.r_analog;
+        }
+    }
+}
+
+// Reports the digital "L" or "R" button of every connected controller as held whenever its
+// analog value exceeds `threshold`, if any. See `Listener::set_synthesize_trigger_click`.
+fn synthesize_trigger_clicks(controllers: &mut [Option<Controller>; 4], threshold: Option<u8>) {
+    let threshold = match threshold {
+        Some(threshold) => threshold,
+        None => return,
+    };
+
+    for controller in controllers.iter_mut() {
+        if let Some(ref mut controller) = *controller {
+            controller.l |= controller.l_analog > threshold;
+            controller.r |= controller.r_analog > threshold;
+        }
+    }
+}
+
+// Returns `true` if a rumble command with `intensities` needs to be written at `now`, given the
+// last command sent and when, if any. See `Listener::rumble_raw`.
+#[cfg(feature = "rumble")]
+fn should_send_rumble(intensities: [u8; 4], last: Option<([u8; 4], Instant)>,
+                      refresh: Option<Duration>, now: Instant) -> bool
+{
+    match last {
+        Some((sent, at)) => {
+            let elapsed = now.checked_duration_since(at).unwrap_or(Duration::from_secs(0));
+            sent != intensities || refresh.map_or(false, |refresh| elapsed >= refresh)
+        },
+        None => true,
+    }
+}
+
+// Bookkeeping of the packets a `Listener` accepted: their total length, the latest one, and
+// whether it repeated the one accepted before it.
+#[derive(Clone, Copy, Debug, Default)]
+struct PacketLog {
+    bytes_read: u64,
+    latest: Option<[u8; PACKET_LEN]>,
+    repeated: bool,
+}
+
+impl PacketLog {
+    // Validates a message of `read` bytes received into `data`, rejecting unrecognized controller
+    // classifications if `strict_kind` is set, and returns the controller data packet at its
+    // start. Only messages that pass every check are recorded.
+    fn accept(&mut self, packet_len: PacketLen, strict_kind: bool, read: usize, data: &[u8])
+        -> Result<[u8; PACKET_LEN], Error>
+    {
+        try!(validate_packet(packet_len, read, data));
+        let mut packet = [0; PACKET_LEN];
+        packet.copy_from_slice(&data[..PACKET_LEN]);
+        if strict_kind {
+            try!(check_kinds(&packet));
+        }
+
+        self.bytes_read += read as u64;
+        self.repeated = self.latest == Some(packet);
+        self.latest = Some(packet);
+        Ok(packet)
+    }
+}
+
+// Checks that every port of a controller data packet reports a recognized controller
+// classification, or none at all. See `Listener::set_strict_kind`.
+fn check_kinds(packet: &[u8; PACKET_LEN]) -> Result<(), Error> {
+    for &port in Port::ALL.iter() {
+        let nibble = packet[1 + port.index() * 9] >> 4;
+        if nibble >= 3 {
+            return Err(Error::UnknownControllerKind(port, nibble));
+        }
+    }
+    Ok(())
+}
+
+// Appends a record of a message read from the adapter to a raw capture. See
+// `Listener::set_raw_capture` for the format.
+fn write_capture_record(writer: &mut Write, data: &[u8]) -> io::Result<()> {
+    let micros = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_micros() as u64);
+    try!(writer.write_all(&micros.to_le_bytes()));
+    try!(writer.write_all(&(data.len() as u16).to_le_bytes()));
+    writer.write_all(data)
+}
+
+// Reads a single message into `data` and returns its length.
+fn read_transfer(handle: &mut DeviceHandle, endpoint: u8, timeout: Duration, data: &mut [u8])
+    -> Result<usize, Error>
+{
+    #[cfg(feature = "tracing")]
+    let span = tracing::trace_span!("read", endpoint = endpoint, bytes = tracing::field::Empty,
+                                    elapsed_us = tracing::field::Empty);
+    #[cfg(feature = "tracing")]
+    let _enter = span.enter();
+    #[cfg(feature = "tracing")]
+    let start = Instant::now();
+
+    let result = handle.read_interrupt(endpoint, data, timeout);
+    #[cfg(feature = "tracing")]
+    {
+        if let Ok(read) = result {
+            span.record("bytes", &read);
+        }
+        span.record("elapsed_us", &(start.elapsed().as_micros() as u64));
+    }
+
+    result.map_err(Error::Usb)
+}
+
+/// The length that messages read from the adapter must have. See `Listener::set_packet_len`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketLen {
+    /// Messages must be exactly the given number of bytes long. Lengths below 37 are never
+    /// accepted, as they cannot hold a full controller data packet.
+    Fixed(usize),
+    /// Messages must be at least 37 bytes long, up to the maximum packet size of the adapter's
+    /// input endpoint.
+    Auto,
+}
+
+impl Default for PacketLen {
+    fn default() -> PacketLen {
+        PacketLen::Fixed(PACKET_LEN)
+    }
+}
+
+/// The states from the two most recent successful reads of a `Listener`, as returned by
+/// `Listener::read_pair`.
+///
+/// Before the first read, both frames have every port empty. After it, `previous` still does, so
+/// controllers connected at that point appear as newly connected when diffing the pair.
+#[derive(Clone, Copy, Debug)]
+pub struct FramePair {
+    /// The states from the read before the most recent one.
+    pub previous: [Option<Controller>; 4],
+    /// The states from the most recent read.
+    pub current: [Option<Controller>; 4],
+}
+
+impl FramePair {
+    // Makes `current` the previous frame and `controllers` the current one.
+    fn push(&mut self, controllers: [Option<Controller>; 4]) {
+        self.previous = self.current;
+        self.current = controllers;
+    }
+}
+
+/// The controller states of a packet along with the moment it was read, as returned by
+/// `Listener::read_timed`.
+#[derive(Clone, Copy, Debug)]
+pub struct TimedFrame {
+    /// The moment the read completed. `Instant` is monotonic, so it is suitable for measuring
+    /// intervals between frames, but it does not correspond to wall-clock time.
+    pub at: Instant,
+    /// The states of the four ports.
+    pub controllers: [Option<Controller>; 4],
+}
+
+/// Returns the 32-character hexadecimal SDL joystick GUID of an adapter with the given device
+/// release number (`bcdDevice`), for looking up or registering mappings in SDL's game controller
+/// database.
+///
+/// The GUID is made of eight little-endian 16-bit words: the bus type (`0x0003` for USB), a name
+/// checksum (left as `0`), the vendor ID, `0`, the product ID, `0`, the device release number,
+/// and `0`. For example, an adapter with a release number of `0x0100` has the GUID
+/// `030000007e0500003703000000010000`.
+pub fn adapter_sdl_guid(bcd_device: u16) -> String {
+    let words = [0x0003, 0, VENDOR_ID, 0, PRODUCT_ID, 0, bcd_device, 0];
+    words.iter().map(|word| format!("{:02x}{:02x}", word & 0xff, word >> 8)).collect()
+}
+
+/// The state of a GameCube controller at a given moment in time.
+///
+/// Note that the hardware will likely never report either extreme of the spectrum for any of the
+/// analog inputs. For example, all `u8` fields may report only within the range of `30` to `225`.
+/// Also, the hardware will likely never report a perfect `127` for the resting position of any of
+/// the joystick axes. Keep in my that this library does not do any analog dead zone correction.
+#[derive(Clone, Copy, Debug)]
+pub struct Controller {
+    /// The classification of this controller.
+    pub kind: ControllerKind,
+    /// "A" button status.
+    pub a: bool,
+    /// "B" button status.
+    pub b: bool,
+    /// "X" button status.
+    pub x: bool,
+    /// "Y" button status.
+    pub y: bool,
+    /// Directional pad up button status.
+    pub up: bool,
+    /// Directional pad down button status.
+    pub down: bool,
+    /// Directional pad left button status.
+    pub left: bool,
+    /// Directional pad right button status.
+    pub right: bool,
+    /// Digital "L" button (full depression) status.
+    pub l: bool,
+    /// Digital "R" button (full depression) status.
+    pub r: bool,
+    /// The level of depression of the analog "L" button, `0` being completely up, `255` being
+    /// completely pressed in.
+    pub l_analog: u8,
+    /// The level of depression of the analog "R" button, `0` being completely up, `255` being
+    /// completely pressed in.
+    pub r_analog: u8,
+    /// "Z" button status.
+    pub z: bool,
+    /// Start button status.
+    pub start: bool,
+    /// The x-axis position of the primary analog joystick, `0` being completely left, `255` being
+    /// completely right.
+    pub stick_x: u8,
+    /// The y-axis position of the primary analog joystick, `0` being completely down, `255` being
+    /// completely up.
+    pub stick_y: u8,
+    /// The x-axis position of the secondary ("C") analog joystick, `0` being completely left,
+    /// `255` being completely right.
+    pub c_stick_x: u8,
+    /// The y-axis position of the secondary ("C") analog joystick, `0` being completely down,
+    /// `255` being completely up.
+    pub c_stick_y: u8,
+}
+
+impl Controller {
+    /// The column names of the rows produced by `to_csv_row`.
+    pub const CSV_HEADER: &'static str = concat!(
+        "kind,a,b,x,y,up,down,left,right,l,r,l_analog,r_analog,z,start,",
+        "stick_x,stick_y,c_stick_x,c_stick_y",
+    );
+
+    /// Assembles a controller state from its decomposed parts, as they would appear in a data
+    /// packet.
+    ///
+    /// `kind_nibble` is the controller classification (only its lower four bits are considered),
+    /// where `0` indicates that no controller is connected and results in `None`.
+    ///
+    /// `buttons` is a bitmask of digital button states:
+    ///
+    /// | Bit | Button | Bit | Button |
+    /// |-----|--------|-----|--------|
+    /// | 0   | A      | 8   | Start  |
+    /// | 1   | B      | 9   | Z      |
+    /// | 2   | X      | 10  | R      |
+    /// | 3   | Y      | 11  | L      |
+    /// | 4   | Left   |     |        |
+    /// | 5   | Right  |     |        |
+    /// | 6   | Down   |     |        |
+    /// | 7   | Up     |     |        |
+    ///
+    /// Bits 12 through 15 are ignored. `sticks` holds `stick_x`, `stick_y`, `c_stick_x`, and
+    /// `c_stick_y`, in that order, and `triggers` holds `l_analog` and `r_analog`.
+    pub fn from_raw(kind_nibble: u8, buttons: u16, sticks: [u8; 4], triggers: [u8; 2])
+        -> Option<Controller>
+    {
+        Controller::parse(&[
+            (kind_nibble & 0x0f) << 4,
+            buttons as u8,
+            (buttons >> 8) as u8,
+            sticks[0],
+            sticks[1],
+            sticks[2],
+            sticks[3],
+            triggers[0],
+            triggers[1],
+        ])
+    }
+
+    /// Returns the analog values of this controller scaled to the full range of 16-bit integers.
+    ///
+    /// Each joystick axis is treated as being centered at `127`. Values at or below the center are
+    /// mapped with `(value - 127) * 32768 / 127` and values above the center with
+    /// `(value - 127) * 32767 / 128`, so `0`, `127`, and `255` become `-32768`, `0`, and `32767`
+    /// respectively. Both halves are scaled separately so that the center is reported as exactly
+    /// `0` and both extremes are reachable.
+    ///
+    /// Each trigger is mapped with `value * 257`, so `0` and `255` become `0` and `65535`.
+    pub fn to_i16_axes(&self) -> Axes16 {
+        Axes16 {
+            stick_x: scale_axis(self.stick_x),
+            stick_y: scale_axis(self.stick_y),
+            c_stick_x: scale_axis(self.c_stick_x),
+            c_stick_y: scale_axis(self.c_stick_y),
+            l_analog: self.l_analog as u16 * 257,
+            r_analog: self.r_analog as u16 * 257,
+        }
+    }
+
+    /// Returns the value of an analog input in the ranges used by SDL's game controller API.
+    ///
+    /// Joystick axes are scaled as in `to_i16_axes`, except that the y-axes are negated: SDL
+    /// treats down as positive while this library treats up as positive. Because `-32768` has no
+    /// positive counterpart, a joystick held completely up reports `-32767` rather than `-32768`.
+    ///
+    /// Triggers are mapped with `value * 32767 / 255`, so `0` and `255` become `0` and `32767`.
+    pub fn sdl_axis(&self, axis: SdlAxis) -> i16 {
+        match axis {
+            SdlAxis::LeftX => scale_axis(self.stick_x),
+            SdlAxis::LeftY => scale_axis(self.stick_y).saturating_neg(),
+            SdlAxis::RightX => scale_axis(self.c_stick_x),
+            SdlAxis::RightY => scale_axis(self.c_stick_y).saturating_neg(),
+            SdlAxis::TriggerLeft => (self.l_analog as i32 * 32767 / 255) as i16,
+            SdlAxis::TriggerRight => (self.r_analog as i32 * 32767 / 255) as i16,
+        }
+    }
+
+    /// Returns a left-right mirrored copy of this controller state.
+    ///
+    /// The left and right directional pad buttons are swapped, as are the digital and analog "L"
+    /// and "R" buttons. The x-axes of both joysticks are reflected around the resting value of
+    /// `127` with `254 - x`, so `127` stays put and `0` becomes `254`. Since `255` has no
+    /// counterpart on the other side of the center, it becomes `0`. All other inputs, including
+    /// up, down, "A", and "B", are untouched.
+    pub fn mirror(&self) -> Controller {
+        Controller {
+            left: self.right,
+            right: self.left,
+            l: self.r,
+            r: self.l,
+            l_analog: self.r_analog,
+            r_analog: self.l_analog,
+            stick_x: 254u8.saturating_sub(self.stick_x),
+            c_stick_x: 254u8.saturating_sub(self.c_stick_x),
+            ..*self
+        }
+    }
+
+    /// Returns a compact JSON object describing this controller state, without requiring any
+    /// additional dependencies.
+    ///
+    /// The object has one key per field, named after the field, in declaration order. `kind` is
+    /// written as a string (such as `"Wired"`), buttons as booleans, and analog values as numbers.
+    pub fn to_json(&self) -> String {
+        format!(concat!(
+            "{{\"kind\":\"{:?}\",\"a\":{},\"b\":{},\"x\":{},\"y\":{},\"up\":{},\"down\":{},",
+            "\"left\":{},\"right\":{},\"l\":{},\"r\":{},\"l_analog\":{},\"r_analog\":{},",
+            "\"z\":{},\"start\":{},\"stick_x\":{},\"stick_y\":{},\"c_stick_x\":{},",
+            "\"c_stick_y\":{}}}",
+        ), self.kind, self.a, self.b, self.x, self.y, self.up, self.down, self.left, self.right,
+           self.l, self.r, self.l_analog, self.r_analog, self.z, self.start, self.stick_x,
+           self.stick_y, self.c_stick_x, self.c_stick_y)
+    }
+
+    /// Returns a scalar between `0.0` and `1.0` summarizing how much input is being applied, which
+    /// is convenient for driving meters and other visualizations.
+    ///
+    /// The result is the weighted sum of:
+    ///
+    /// * `0.3` × the distance of the primary joystick from `(127, 127)`, divided by `127` and
+    ///   capped at `1.0`
+    /// * `0.2` × the same measure for the "C" joystick
+    /// * `0.1` × `l_analog / 255` and `0.1` × `r_analog / 255`
+    /// * `0.3` × the fraction of the twelve digital buttons that are held
+    ///
+    /// A controller at rest yields `0.0`, or very close to it given imperfect joystick centering.
+    pub fn activity(&self) -> f32 {
+        fn displacement(x: u8, y: u8) -> f32 {
+            let (dx, dy) = (x as f32 - 127.0, y as f32 - 127.0);
+            ((dx * dx + dy * dy).sqrt() / 127.0).min(1.0)
+        }
+
+        let buttons = Button::ALL.iter().filter(|&&b| self.is_pressed(b)).count();
+
+        0.3 * displacement(self.stick_x, self.stick_y) +
+            0.2 * displacement(self.c_stick_x, self.c_stick_y) +
+            0.1 * (self.l_analog as f32 / 255.0) +
+            0.1 * (self.r_analog as f32 / 255.0) +
+            0.3 * (buttons as f32 / Button::ALL.len() as f32)
+    }
+
+    /// Returns a copy of this controller state with all six analog values clamped to
+    /// `min..=max`, leaving the digital buttons untouched.
+    ///
+    /// The hardware usually reports analog values within roughly `30` to `225`, so clamping to such
+    /// a band before normalizing rejects outliers caused by dropouts or corrupt reads.
+    pub fn clamp_analog(&self, min: u8, max: u8) -> Controller {
+        let mut controller = *self;
+        for &axis in Axis::ALL.iter() {
+            let value = controller.axis_mut(axis);
+            *value = cmp::max(min, cmp::min(max, *value));
+        }
+        controller
+    }
+
+    /// Returns this controller state as a row of comma-separated values, with columns as named by
+    /// `CSV_HEADER`.
+    ///
+    /// `kind` is written by name (such as `Wired`), buttons as `0` or `1`, and analog values as
+    /// integers. No trailing newline is included.
+    pub fn to_csv_row(&self) -> String {
+        format!("{:?},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                self.kind, self.a as u8, self.b as u8, self.x as u8, self.y as u8, self.up as u8,
+                self.down as u8, self.left as u8, self.right as u8, self.l as u8, self.r as u8,
+                self.l_analog, self.r_analog, self.z as u8, self.start as u8, self.stick_x,
+                self.stick_y, self.c_stick_x, self.c_stick_y)
+    }
+
+    /// Returns the position of the primary analog joystick.
+    pub fn stick(&self) -> Stick {
+        Stick { x: self.stick_x, y: self.stick_y }
+    }
+
+    /// Returns the x-axis position of the primary analog joystick as a signed offset from the
+    /// center, computed as `stick_x - 127`. The result ranges from `-127` to `128`.
+    pub fn stick_x_signed(&self) -> i16 {
+        self.stick_x as i16 - 127
+    }
+
+    /// Returns the y-axis position of the primary analog joystick as a signed offset from the
+    /// center. See `stick_x_signed`.
+    pub fn stick_y_signed(&self) -> i16 {
+        self.stick_y as i16 - 127
+    }
+
+    /// Returns the x-axis position of the secondary ("C") analog joystick as a signed offset from
+    /// the center. See `stick_x_signed`.
+    pub fn c_stick_x_signed(&self) -> i16 {
+        self.c_stick_x as i16 - 127
+    }
+
+    /// Returns the y-axis position of the secondary ("C") analog joystick as a signed offset from
+    /// the center. See `stick_x_signed`.
+    pub fn c_stick_y_signed(&self) -> i16 {
+        self.c_stick_y as i16 - 127
+    }
+
+    /// Returns `true` if the primary analog joystick is within `tolerance` of the given notch on
+    /// both axes.
+    ///
+    /// Positions are compared as offsets from the center at `127` (see `Notch::offset`), so a
+    /// stick that rests off-center should first be corrected, for example by capturing its origin
+    /// with `Listener::capture_origin`.
+    pub fn is_near_notch(&self, notch: Notch, tolerance: u8) -> bool {
+        let (notch_x, notch_y) = notch.offset();
+        let (x, y) = (self.stick_x_signed(), self.stick_y_signed());
+        let tolerance = tolerance as i16;
+        (x - notch_x as i16).abs() <= tolerance && (y - notch_y as i16).abs() <= tolerance
+    }
+
+    /// Returns the 8-way direction that the player is steering in with the directional pad or
+    /// the primary analog joystick, or `None` if neither is steering.
+    ///
+    /// The directional pad takes priority: if it points anywhere, the joystick is ignored.
+    /// Opposite directions held on the pad cancel each other out, so holding both left and right
+    /// counts as neither.
+    ///
+    /// Otherwise, the joystick is steering if its distance from the center, with each axis
+    /// normalized to `-1.0..1.0` as by `Stick::normalized`, exceeds `deadzone`. Its angle is then
+    /// snapped to the nearest of the eight directions, each covering a 45 degree sector.
+    pub fn movement_direction(&self, deadzone: f32) -> Option<Direction> {
+        let pad_x = self.right as i8 - self.left as i8;
+        let pad_y = self.up as i8 - self.down as i8;
+        if pad_x != 0 || pad_y != 0 {
+            return Direction::from_signs(pad_x, pad_y);
+        }
+
+        let (x, y) = self.stick().normalized();
+        if (x * x + y * y).sqrt() <= deadzone {
+            return None;
+        }
+
+        let octant = (y.atan2(x) / std::f32::consts::FRAC_PI_4).round() as i8;
+        Some(match (octant + 8) % 8 {
+            0 => Direction::Right,
+            1 => Direction::UpRight,
+            2 => Direction::Up,
+            3 => Direction::UpLeft,
+            4 => Direction::Left,
+            5 => Direction::DownLeft,
+            6 => Direction::Down,
+            _ => Direction::DownRight,
+        })
+    }
+
+    /// Returns the position of the secondary ("C") analog joystick.
+    pub fn c_stick(&self) -> Stick {
+        Stick { x: self.c_stick_x, y: self.c_stick_y }
+    }
+
+    /// Returns the level of depression of the analog "L" button.
+    pub fn l_trigger(&self) -> Trigger {
+        Trigger(self.l_analog)
+    }
+
+    /// Returns the level of depression of the analog "R" button.
+    pub fn r_trigger(&self) -> Trigger {
+        Trigger(self.r_analog)
+    }
+
+    /// Returns the level of depression of the analog "L" button as a percentage from `0` to `100`,
+    /// computed as `value * 100 / 255` and rounded down.
+    pub fn l_analog_percent(&self) -> u8 {
+        trigger_percent(self.l_analog)
+    }
+
+    /// Returns the level of depression of the analog "R" button as a percentage from `0` to `100`,
+    /// computed as `value * 100 / 255` and rounded down.
+    pub fn r_analog_percent(&self) -> u8 {
+        trigger_percent(self.r_analog)
+    }
+
+    /// Returns the x-axis position of the primary analog joystick as a signed percentage of its
+    /// deflection from the center (`127`), from `-100` to `100`.
+    ///
+    /// The percentage is computed as `(value - 127) * 100 / 127`, rounded towards zero. As `255`
+    /// lies one step further from the center than `0`, the result is clamped to `100`.
+    pub fn stick_x_percent(&self) -> i8 {
+        stick_percent(self.stick_x)
+    }
+
+    /// Returns the y-axis position of the primary analog joystick as a signed percentage. See
+    /// `stick_x_percent`.
+    pub fn stick_y_percent(&self) -> i8 {
+        stick_percent(self.stick_y)
+    }
+
+    /// Returns the x-axis position of the secondary ("C") analog joystick as a signed percentage.
+    /// See `stick_x_percent`.
+    pub fn c_stick_x_percent(&self) -> i8 {
+        stick_percent(self.c_stick_x)
+    }
+
+    /// Returns the y-axis position of the secondary ("C") analog joystick as a signed percentage.
+    /// See `stick_x_percent`.
+    pub fn c_stick_y_percent(&self) -> i8 {
+        stick_percent(self.c_stick_y)
+    }
+
+    /// Interpolates between two controller states, for example to resample a recording at a
+    /// different rate than it was captured.
+    ///
+    /// The six analog values are interpolated linearly as `a + (b - a) * t`, rounded to the
+    /// nearest integer. Fields that cannot be interpolated (the digital buttons and `kind`) are
+    /// taken from the nearer state: `a` if `t < 0.5`, otherwise `b`. `t` is clamped to
+    /// `0.0..1.0`.
+    pub fn lerp(a: &Controller, b: &Controller, t: f32) -> Controller {
+        let t = t.max(0.0).min(1.0);
+        let mut controller = if t < 0.5 { *a } else { *b };
+
+        for &axis in Axis::ALL.iter() {
+            let (from, to) = (a.axis(axis) as f32, b.axis(axis) as f32);
+            *controller.axis_mut(axis) = (from + (to - from) * t).round() as u8;
+        }
+
+        controller
+    }
+
+    /// Returns `true` if the given digital button is held.
+    pub fn is_pressed(&self, button: Button) -> bool {
+        match button {
+            Button::A => self.a,
+            Button::B => self.b,
+            Button::X => self.x,
+            Button::Y => self.y,
+            Button::Start => self.start,
+            Button::Z => self.z,
+            Button::L => self.l,
+            Button::R => self.r,
+            Button::Up => self.up,
+            Button::Down => self.down,
+            Button::Left => self.left,
+            Button::Right => self.right,
+        }
+    }
+
+    /// Returns `true` if A, B, X, Y, and Start are all held at once, the combination commonly used
+    /// to soft-reset GameCube games. Other buttons may be held as well.
+    pub fn is_reset_combo(&self) -> bool {
+        self.a && self.b && self.x && self.y && self.start
+    }
+
+    /// Returns `false` if this state is physically impossible and therefore likely the result of a
+    /// corrupt read, which a flaky connection occasionally produces.
+    ///
+    /// A state is implausible if any of the following holds:
+    ///
+    /// * All twelve digital buttons are held at once.
+    /// * Both up and down are held on the directional pad, which rocks on a single pivot.
+    /// * Both left and right are held on the directional pad.
+    ///
+    /// Analog values cannot be judged this way, as every value is reachable.
+    pub fn is_plausible(&self) -> bool {
+        let all_held = Button::ALL.iter().all(|&button| self.is_pressed(button));
+        !all_held && !(self.up && self.down) && !(self.left && self.right)
+    }
+
+    /// Returns `true` if the GameCube button mapped to the given standard gamepad button is held.
+    /// Buttons without a counterpart are never held. See `StandardButton` for the mapping.
+    pub fn standard(&self, button: StandardButton) -> bool {
+        button.button().map_or(false, |button| self.is_pressed(button))
+    }
+
+    /// Returns the raw value of the given analog input.
+    pub fn axis(&self, axis: Axis) -> u8 {
+        match axis {
+            Axis::StickX => self.stick_x,
+            Axis::StickY => self.stick_y,
+            Axis::CStickX => self.c_stick_x,
+            Axis::CStickY => self.c_stick_y,
+            Axis::LAnalog => self.l_analog,
+            Axis::RAnalog => self.r_analog,
+        }
+    }
+
+    fn axis_mut(&mut self, axis: Axis) -> &mut u8 {
+        match axis {
+            Axis::StickX => &mut self.stick_x,
+            Axis::StickY => &mut self.stick_y,
+            Axis::CStickX => &mut self.c_stick_x,
+            Axis::CStickY => &mut self.c_stick_y,
+            Axis::LAnalog => &mut self.l_analog,
+            Axis::RAnalog => &mut self.r_analog,
+        }
+    }
+
+    /// Returns the input events that transform the `previous` state into this one.
+    ///
+    /// Button events come first, in the order of `Button::ALL`, followed by axis events in the
+    /// order of `Axis::ALL`. An axis is only reported if its value differs from the previous value
+    /// by more than `threshold`; a `threshold` of `0` reports every change.
+    pub fn diff(&self, previous: &Controller, threshold: u8) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+
+        for &button in Button::ALL.iter() {
+            match (previous.is_pressed(button), self.is_pressed(button)) {
+                (false, true) => events.push(InputEvent::ButtonDown(button)),
+                (true, false) => events.push(InputEvent::ButtonUp(button)),
+                _ => {},
+            }
+        }
+
+        for &axis in Axis::ALL.iter() {
+            let (old, new) = (previous.axis(axis), self.axis(axis));
+            let delta = if new > old { new - old } else { old - new };
+            if delta > threshold {
+                events.push(InputEvent::AxisMoved(axis, new));
+            }
+        }
+
+        events
+    }
+
+    /// Returns human-readable descriptions of every field that differs from `previous`, such as
+    /// `"A pressed"`, `"Start released"`, or `"stick_x 120 -> 200"`.
+    ///
+    /// This is a debugging aid that allocates freely; use `diff` to process changes in the hot
+    /// path.
+    pub fn describe_changes(&self, previous: &Controller) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        if self.kind != previous.kind {
+            changes.push(format!("kind {:?} -> {:?}", previous.kind, self.kind));
+        }
+
+        for &button in Button::ALL.iter() {
+            match (previous.is_pressed(button), self.is_pressed(button)) {
+                (false, true) => changes.push(format!("{:?} pressed", button)),
+                (true, false) => changes.push(format!("{:?} released", button)),
+                _ => {},
+            }
+        }
+
+        for &axis in Axis::ALL.iter() {
+            let (old, new) = (previous.axis(axis), self.axis(axis));
+            if old != new {
+                changes.push(format!("{} {} -> {}", axis.field_name(), old, new));
+            }
+        }
+
+        changes
+    }
+
+    // # Panics
+    //
+    // Panics if `data` is not at least 9 bytes. Callers only ever pass fixed-size blocks, which is
+    // checked in debug builds.
+    fn parse(data: &[u8]) -> Option<Controller> {
+        debug_assert_eq!(data.len(), 9);
+
+        let kind = match ControllerKind::from_nibble(data[0] >> 4) {
+            Some(kind) => kind,
+            None => return None,
+        };
+
+        Some(Controller {
+            kind: kind,
+            a: data[1] & (1 << 0) != 0,
+            b: data[1] & (1 << 1) != 0,
+            x: data[1] & (1 << 2) != 0,
+            y: data[1] & (1 << 3) != 0,
+            left: data[1] & (1 << 4) != 0,
+            right: data[1] & (1 << 5) != 0,
+            down: data[1] & (1 << 6) != 0,
+            up: data[1] & (1 << 7) != 0,
+            start: data[2] & (1 << 0) != 0,
+            z: data[2] & (1 << 1) != 0,
+            r: data[2] & (1 << 2) != 0,
+            l: data[2] & (1 << 3) != 0,
+            stick_x: data[3],
+            stick_y: data[4],
+            c_stick_x: data[5],
+            c_stick_y: data[6],
+            l_analog: data[7],
+            r_analog: data[8],
+        })
+    }
+
+    // Encodes this state as the 9-byte block `parse` decodes.
+    fn to_block(&self) -> [u8; 9] {
+        let bit = |pressed: bool, shift: u8| (pressed as u8) << shift;
+
+        [
+            self.kind.to_nibble() << 4,
+            bit(self.a, 0) | bit(self.b, 1) | bit(self.x, 2) | bit(self.y, 3) |
+                bit(self.left, 4) | bit(self.right, 5) | bit(self.down, 6) | bit(self.up, 7),
+            bit(self.start, 0) | bit(self.z, 1) | bit(self.r, 2) | bit(self.l, 3),
+            self.stick_x,
+            self.stick_y,
+            self.c_stick_x,
+            self.c_stick_y,
+            self.l_analog,
+            self.r_analog,
+        ]
+    }
+
+    /// Returns the state of the controller connected to `port` in a raw data packet, or `None` if
+    /// the port is empty.
+    ///
+    /// Only the port's own 9-byte block is decoded, which is cheaper than parsing the full packet
+    /// when only one port is of interest.
+    pub fn parse_port(data: &[u8; 37], port: Port) -> Option<Controller> {
+        let start = 1 + port.index() * 9;
+        Controller::parse(&data[start..start + 9])
+    }
+
+    // Taking a fixed-size buffer means the slicing below cannot panic. Packets of any other length
+    // are rejected with `Error::InvalidPacket` before reaching this point.
+    fn parse_packet(data: &[u8; PACKET_LEN]) -> [Option<Controller>; 4] {
+        [
+            Controller::parse(&data[1..10]),
+            Controller::parse(&data[10..19]),
+            Controller::parse(&data[19..28]),
+            Controller::parse(&data[28..37])
+        ]
+    }
+
+    /// Returns an iterator that lazily parses the state of each port from a raw data packet, from
+    /// `Port::One` to `Port::Four`.
+    ///
+    /// `data` is validated up front: unless it is exactly 37 bytes long and starts with the
+    /// controller data report ID (`0x21`), the iterator yields nothing. Otherwise it yields four
+    /// items.
+    pub fn iter_packet(data: &[u8]) -> PacketIter {
+        let valid = data.len() == PACKET_LEN && data[0] == REPORT_ID;
+        let blocks = if valid { &data[1..] } else { &[] };
+        PacketIter {
+            blocks: blocks.chunks(9),
+        }
+    }
+
+    /// Returns the classification of the controller connected to each port of a raw data packet,
+    /// or `None` for empty ports.
+    ///
+    /// Only the status byte at the start of each port's block is inspected, which is cheaper than
+    /// parsing the full controller states when only port occupancy is of interest.
+    pub fn port_kinds(packet: &[u8; 37]) -> [Option<ControllerKind>; 4] {
+        [
+            ControllerKind::from_nibble(packet[1] >> 4),
+            ControllerKind::from_nibble(packet[10] >> 4),
+            ControllerKind::from_nibble(packet[19] >> 4),
+            ControllerKind::from_nibble(packet[28] >> 4),
+        ]
+    }
+}
+
+/// One of the four controller ports of an adapter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Port {
+    /// The first port, labeled "1" on the adapter.
+    One,
+    /// The second port.
+    Two,
+    /// The third port.
+    Three,
+    /// The fourth port.
+    Four,
+}
+
+impl Port {
+    /// Every port, in order.
+    pub const ALL: [Port; 4] = [Port::One, Port::Two, Port::Three, Port::Four];
+
+    /// Returns the zero-based index of this port into arrays such as the one returned by
+    /// `Listener::read`.
+    pub fn index(&self) -> usize {
+        match *self {
+            Port::One => 0,
+            Port::Two => 1,
+            Port::Three => 2,
+            Port::Four => 3,
+        }
+    }
+
+    /// Returns the port with the given zero-based index, or `None` if `index` is not below `4`.
+    pub fn from_index(index: usize) -> Option<Port> {
+        Port::ALL.get(index).cloned()
+    }
+}
+
+/// A digital button of a GameCube controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Button {
+    /// "A" button.
+    A,
+    /// "B" button.
+    B,
+    /// "X" button.
+    X,
+    /// "Y" button.
+    Y,
+    /// Start button.
+    Start,
+    /// "Z" button.
+    Z,
+    /// Digital "L" button (full depression).
+    L,
+    /// Digital "R" button (full depression).
+    R,
+    /// Directional pad up button.
+    Up,
+    /// Directional pad down button.
+    Down,
+    /// Directional pad left button.
+    Left,
+    /// Directional pad right button.
+    Right,
+}
+
+impl Button {
+    /// Every button, in declaration order.
+    pub const ALL: [Button; 12] = [
+        Button::A, Button::B, Button::X, Button::Y, Button::Start, Button::Z, Button::L, Button::R,
+        Button::Up, Button::Down, Button::Left, Button::Right,
+    ];
+
+    /// Returns the index of this button, for use with per-button state tables such as
+    /// `[T; 12]`.
+    ///
+    /// Indices are stable and follow declaration order: `A` is `0`, `B` is `1`, `X` is `2`, `Y` is
+    /// `3`, `Start` is `4`, `Z` is `5`, `L` is `6`, `R` is `7`, `Up` is `8`, `Down` is `9`, `Left`
+    /// is `10`, and `Right` is `11`.
+    pub fn index(&self) -> usize {
+        match *self {
+            Button::A => 0,
+            Button::B => 1,
+            Button::X => 2,
+            Button::Y => 3,
+            Button::Start => 4,
+            Button::Z => 5,
+            Button::L => 6,
+            Button::R => 7,
+            Button::Up => 8,
+            Button::Down => 9,
+            Button::Left => 10,
+            Button::Right => 11,
+        }
+    }
+
+    /// Returns the button with the given index, or `None` if `index` is not below `12`. See
+    /// `index`.
+    pub fn from_index(index: usize) -> Option<Button> {
+        Button::ALL.get(index).cloned()
+    }
+}
+
+/// A button of the generic gamepad layout used by many game engines, which is modeled after the
+/// Xbox controller. See `Controller::standard`.
+///
+/// The face buttons are named by their position in the diamond. The GameCube's face buttons are
+/// laid out differently, so they are mapped by their conventional role instead: `A` confirms and
+/// `B` cancels, as `South` and `East` do.
+///
+/// | Standard        | GameCube     |
+/// |-----------------|--------------|
+/// | `South`         | `A`          |
+/// | `East`          | `B`          |
+/// | `West`          | `Y`          |
+/// | `North`         | `X`          |
+/// | `LeftShoulder`  | (none)       |
+/// | `RightShoulder` | `Z`          |
+/// | `LeftTrigger`   | digital `L`  |
+/// | `RightTrigger`  | digital `R`  |
+/// | `Start`         | `Start`      |
+/// | `DPadUp`, etc.  | `Up`, etc.   |
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StandardButton {
+    /// The bottom face button.
+    South,
+    /// The right face button.
+    East,
+    /// The left face button.
+    West,
+    /// The top face button.
+    North,
+    /// The left bumper.
+    LeftShoulder,
+    /// The right bumper.
+    RightShoulder,
+    /// The left trigger, pressed fully.
+    LeftTrigger,
+    /// The right trigger, pressed fully.
+    RightTrigger,
+    /// The start (or menu) button.
+    Start,
+    /// Directional pad up button.
+    DPadUp,
+    /// Directional pad down button.
+    DPadDown,
+    /// Directional pad left button.
+    DPadLeft,
+    /// Directional pad right button.
+    DPadRight,
+}
+
+impl StandardButton {
+    /// Returns the GameCube button that this button is mapped to, or `None` if the GameCube
+    /// controller has no counterpart (as for `LeftShoulder`).
+    pub fn button(&self) -> Option<Button> {
+        match *self {
+            StandardButton::South => Some(Button::A),
+            StandardButton::East => Some(Button::B),
+            StandardButton::West => Some(Button::Y),
+            StandardButton::North => Some(Button::X),
+            StandardButton::LeftShoulder => None,
+            StandardButton::RightShoulder => Some(Button::Z),
+            StandardButton::LeftTrigger => Some(Button::L),
+            StandardButton::RightTrigger => Some(Button::R),
+            StandardButton::Start => Some(Button::Start),
+            StandardButton::DPadUp => Some(Button::Up),
+            StandardButton::DPadDown => Some(Button::Down),
+            StandardButton::DPadLeft => Some(Button::Left),
+            StandardButton::DPadRight => Some(Button::Right),
+        }
+    }
+}
+
+/// An analog input of a GameCube controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// The x-axis of the primary analog joystick.
+    StickX,
+    /// The y-axis of the primary analog joystick.
+    StickY,
+    /// The x-axis of the secondary ("C") analog joystick.
+    CStickX,
+    /// The y-axis of the secondary ("C") analog joystick.
+    CStickY,
+    /// The analog "L" button.
+    LAnalog,
+    /// The analog "R" button.
+    RAnalog,
+}
+
+impl Axis {
+    /// Every axis, in declaration order.
+    pub const ALL: [Axis; 6] = [
+        Axis::StickX, Axis::StickY, Axis::CStickX, Axis::CStickY, Axis::LAnalog, Axis::RAnalog,
+    ];
+
+    // The name of the `Controller` field holding this axis.
+    fn field_name(&self) -> &'static str {
+        match *self {
+            Axis::StickX => "stick_x",
+            Axis::StickY => "stick_y",
+            Axis::CStickX => "c_stick_x",
+            Axis::CStickY => "c_stick_y",
+            Axis::LAnalog => "l_analog",
+            Axis::RAnalog => "r_analog",
+        }
+    }
+
+    // The resting value of this axis.
+    fn neutral(&self) -> u8 {
+        match *self {
+            Axis::LAnalog | Axis::RAnalog => 0,
+            _ => 127,
+        }
+    }
+}
+
+/// The states of the four controller ports of an adapter at a given moment in time.
+///
+/// This wraps the array returned by `Listener::read`, where each element is `None` if no
+/// controller is connected to the corresponding port.
+#[derive(Clone, Copy, Debug)]
+pub struct Controllers(pub [Option<Controller>; 4]);
+
+impl Controllers {
+    /// Returns the wrapped array of port states.
+    pub fn as_array(&self) -> &[Option<Controller>; 4] {
+        &self.0
+    }
+
+    /// Returns `true` if a controller is connected to at least one port.
+    pub fn any_connected(&self) -> bool {
+        self.0.iter().any(Option::is_some)
+    }
+
+    /// Returns `true` if no controller is connected to any port, in which case there is nothing
+    /// to process.
+    pub fn is_empty(&self) -> bool {
+        !self.any_connected()
+    }
+
+    /// Returns `true` if a controller is connected to every port.
+    pub fn all_connected(&self) -> bool {
+        self.0.iter().all(Option::is_some)
+    }
+
+    /// Returns `true` if any connected controller holds `button`.
+    pub fn any_pressed(&self, button: Button) -> bool {
+        self.0.iter().any(|c| c.map_or(false, |c| c.is_pressed(button)))
+    }
+
+    /// Returns `true` if every connected controller holds `button`. Empty ports are ignored, but
+    /// `false` is returned if no controller is connected at all.
+    pub fn all_pressed(&self, button: Button) -> bool {
+        self.any_connected() && self.0.iter().all(|c| c.map_or(true, |c| c.is_pressed(button)))
+    }
+
+    /// Returns the kind of the controller connected to each port, or `None` for empty ports.
+    pub fn kinds(&self) -> [Option<ControllerKind>; 4] {
+        let mut kinds = [None; 4];
+        for (kind, controller) in kinds.iter_mut().zip(self.0.iter()) {
+            *kind = controller.map(|c| c.kind);
+        }
+        kinds
+    }
+
+    /// Returns the number of connected controllers of each kind, in the order `(wired, wireless,
+    /// unknown)`.
+    pub fn count_by_kind(&self) -> (usize, usize, usize) {
+        let mut counts = (0, 0, 0);
+        for kind in self.kinds().iter() {
+            match *kind {
+                Some(ControllerKind::Wired) => counts.0 += 1,
+                Some(ControllerKind::Wireless) => counts.1 += 1,
+                Some(ControllerKind::Unknown) => counts.2 += 1,
+                None => {},
+            }
+        }
+        counts
+    }
+
+    /// Returns a compact summary of port occupancy, for example `"[Wired][--][Wireless][--]"`.
+    ///
+    /// Each port is rendered in order as a bracketed label: `Wired`, `Wireless`, `Unknown`, or `--`
+    /// for an empty port. This format is stable.
+    pub fn status_line(&self) -> String {
+        let mut line = String::new();
+        for kind in self.kinds().iter() {
+            line.push('[');
+            line.push_str(match *kind {
+                Some(ControllerKind::Wired) => "Wired",
+                Some(ControllerKind::Wireless) => "Wireless",
+                Some(ControllerKind::Unknown) => "Unknown",
+                None => "--",
+            });
+            line.push(']');
+        }
+        line
+    }
+
+    /// Returns the input events that transform the `previous` frame into this one, tagged with the
+    /// port they occurred on.
+    ///
+    /// Events are grouped by port, from `Port::One` to `Port::Four`. A port that became occupied
+    /// reports a lone `Connected` event, and one that became empty reports a lone `Disconnected`
+    /// event. Otherwise the port's events are those of `Controller::diff` with the given
+    /// `threshold`.
+    pub fn diff(&self, previous: &Controllers, threshold: u8) -> Vec<(Port, InputEvent)> {
+        let mut events = Vec::new();
+
+        for &port in Port::ALL.iter() {
+            match (previous.0[port.index()], self.0[port.index()]) {
+                (None, Some(controller)) => {
+                    events.push((port, InputEvent::Connected(controller.kind)));
+                },
+                (Some(_), None) => events.push((port, InputEvent::Disconnected)),
+                (Some(previous), Some(controller)) => {
+                    for event in controller.diff(&previous, threshold) {
+                        events.push((port, event));
+                    }
+                },
+                (None, None) => {},
+            }
+        }
+
+        events
+    }
+
+    /// Returns the events that bring every connected controller from a neutral state (nothing
+    /// held, sticks centered, and triggers up) to its state in this frame, tagged with the port
+    /// they occurred on.
+    ///
+    /// For each connected port, a `Connected` event is followed by the events of
+    /// `Controller::diff` against the neutral state with the given `threshold`. Unlike `diff`, this
+    /// reports inputs that were already held on the first frame, such as a button held while the
+    /// program starts. This is opt-in, as many applications would rather ignore such inputs until
+    /// they are released.
+    pub fn initial_events(&self, threshold: u8) -> Vec<(Port, InputEvent)> {
+        let mut events = Vec::new();
+
+        for (port, controller) in self.iter() {
+            if let Some(controller) = controller {
+                events.push((port, InputEvent::Connected(controller.kind)));
+                let neutral = ControllerBuilder::new().kind(controller.kind).build();
+                for event in controller.diff(&neutral, threshold) {
+                    events.push((port, event));
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Combines two frames port by port, for example to let two physical controllers drive one
+    /// logical player.
+    ///
+    /// A port that is connected in only one frame takes that frame's state. If it is connected in
+    /// both, each digital button is held if it is held in either frame, and each analog input
+    /// takes the value that lies further from its neutral position (`127` for the sticks, each
+    /// axis considered separately, and `0` for the triggers), preferring this frame's value on a
+    /// tie. The controller kind is taken from this frame.
+    pub fn merge(&self, other: &Controllers) -> Controllers {
+        let mut merged = self.0;
+
+        for (merged, other) in merged.iter_mut().zip(other.0.iter()) {
+            *merged = match (*merged, *other) {
+                (Some(mut controller), Some(other)) => {
+                    controller.a |= other.a;
+                    controller.b |= other.b;
+                    controller.x |= other.x;
+                    controller.y |= other.y;
+                    controller.start |= other.start;
+                    controller.z |= other.z;
+                    controller.l |= other.l;
+                    controller.r |= other.r;
+                    controller.up |= other.up;
+                    controller.down |= other.down;
+                    controller.left |= other.left;
+                    controller.right |= other.right;
+
+                    for &axis in Axis::ALL.iter() {
+                        let distance = |value: u8| (value as i16 - axis.neutral() as i16).abs();
+                        if distance(other.axis(axis)) > distance(controller.axis(axis)) {
+                            *controller.axis_mut(axis) = other.axis(axis);
+                        }
+                    }
+
+                    Some(controller)
+                },
+                (merged, other) => merged.or(other),
+            };
+        }
+
+        Controllers(merged)
+    }
+
+    /// Returns a 64-bit checksum of the states of all four ports, including which are connected.
+    ///
+    /// The checksum is the FNV-1a hash of each port's presence byte followed by its state encoded
+    /// as in a data packet, so it is stable across runs, platforms, and versions of Rust. Equal
+    /// frames always produce equal checksums, which makes it suitable for cheaply comparing frames,
+    /// for example in rollback netcode.
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut feed = |byte: u8| {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        };
+
+        for controller in self.0.iter() {
+            match *controller {
+                Some(ref controller) => {
+                    feed(1);
+                    for &byte in controller.to_block().iter() {
+                        feed(byte);
+                    }
+                },
+                None => feed(0),
+            }
+        }
+
+        hash
+    }
+
+    /// Encodes this frame as a fixed-size, 37-byte blob, for passing controller states between
+    /// processes. See `unpack`.
+    ///
+    /// The first byte is a presence mask, where bit `n` (`0x01` being bit `0`) is set if the
+    /// port with index `n` is connected. It is followed by one 9-byte block per port, from
+    /// `Port::One` to `Port::Four`. A connected port's block is encoded exactly as in the
+    /// adapter's data packets, and an empty port's block is all zeros.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut blob = vec![0; PACKET_LEN];
+
+        for (index, controller) in self.0.iter().enumerate() {
+            if let Some(ref controller) = *controller {
+                blob[0] |= 1 << index;
+                blob[1 + index * 9..10 + index * 9].copy_from_slice(&controller.to_block());
+            }
+        }
+
+        blob
+    }
+
+    /// Decodes a frame encoded by `pack`.
+    ///
+    /// `Error::InvalidPacket` is returned if `data` is not exactly 37 bytes long, or if a port
+    /// marked as connected does not hold a valid controller classification.
+    pub fn unpack(data: &[u8]) -> Result<Controllers, Error> {
+        let invalid = Error::InvalidPacket { read: data.len() };
+        if data.len() != PACKET_LEN {
+            return Err(invalid);
+        }
+
+        let mut controllers = [None; 4];
+        for (index, controller) in controllers.iter_mut().enumerate() {
+            if data[0] & (1 << index) != 0 {
+                *controller = Controller::parse(&data[1 + index * 9..10 + index * 9]);
+                if controller.is_none() {
+                    return Err(invalid);
+                }
+            }
+        }
+
+        Ok(Controllers(controllers))
+    }
+
+    /// Returns an iterator over every port and its state, from `Port::One` to `Port::Four`.
+    pub fn iter(&self) -> PortIter {
+        PortIter { controllers: self.0, next: 0 }
+    }
+}
+
+impl From<[Option<Controller>; 4]> for Controllers {
+    fn from(controllers: [Option<Controller>; 4]) -> Controllers {
+        Controllers(controllers)
+    }
+}
+
+impl From<Controllers> for [Option<Controller>; 4] {
+    fn from(controllers: Controllers) -> [Option<Controller>; 4] {
+        controllers.0
+    }
+}
+
+impl IntoIterator for Controllers {
+    type Item = (Port, Option<Controller>);
+    type IntoIter = PortIter;
+
+    fn into_iter(self) -> PortIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Controllers {
+    type Item = (Port, Option<Controller>);
+    type IntoIter = PortIter;
+
+    fn into_iter(self) -> PortIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the port states of a raw data packet. See `Controller::iter_packet`.
+#[derive(Clone, Debug)]
+pub struct PacketIter<'a> {
+    blocks: Chunks<'a, u8>,
+}
+
+impl<'a> Iterator for PacketIter<'a> {
+    type Item = Option<Controller>;
+
+    fn next(&mut self) -> Option<Option<Controller>> {
+        self.blocks.next().map(Controller::parse)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.blocks.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for PacketIter<'a> {}
+
+/// An iterator over the ports of `Controllers` and their states, from `Port::One` to `Port::Four`.
+#[derive(Clone, Debug)]
+pub struct PortIter {
+    controllers: [Option<Controller>; 4],
+    next: usize,
+}
+
+impl Iterator for PortIter {
+    type Item = (Port, Option<Controller>);
+
+    fn next(&mut self) -> Option<(Port, Option<Controller>)> {
+        let port = match Port::from_index(self.next) {
+            Some(port) => port,
+            None => return None,
+        };
+        self.next += 1;
+        Some((port, self.controllers[port.index()]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = 4 - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for PortIter {}
+
+/// The analog values of a `Controller` scaled to 16-bit integers by `Controller::to_i16_axes`.
+///
+/// Joystick axes keep the orientation of their `Controller` counterparts (left and down are
+/// negative).
 #[derive(Clone, Copy, Debug)]
+pub struct Axes16 {
+    /// The x-axis position of the primary analog joystick.
+    pub stick_x: i16,
+    /// The y-axis position of the primary analog joystick.
+    pub stick_y: i16,
+    /// The x-axis position of the secondary ("C") analog joystick.
+    pub c_stick_x: i16,
+    /// The y-axis position of the secondary ("C") analog joystick.
+    pub c_stick_y: i16,
+    /// The level of depression of the analog "L" button.
+    pub l_analog: u16,
+    /// The level of depression of the analog "R" button.
+    pub r_analog: u16,
+}
+
+/// The position of an analog joystick, with the same ranges as the corresponding `Controller`
+/// fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stick {
+    /// The x-axis position, `0` being completely left, `255` being completely right.
+    pub x: u8,
+    /// The y-axis position, `0` being completely down, `255` being completely up.
+    pub y: u8,
+}
+
+impl Stick {
+    /// Returns the position with each axis mapped from `0..255` to `-1.0..1.0` as
+    /// `(value - 127) / 127`, treating `127` as the center. Since `255` lies one step further from
+    /// the center than `0`, results are clamped to `1.0`.
+    pub fn normalized(&self) -> (f32, f32) {
+        fn normalize(value: u8) -> f32 {
+            ((value as f32 - 127.0) / 127.0).min(1.0)
+        }
+
+        (normalize(self.x), normalize(self.y))
+    }
+}
+
+impl From<Stick> for (u8, u8) {
+    fn from(stick: Stick) -> (u8, u8) {
+        (stick.x, stick.y)
+    }
+}
+
+impl From<(u8, u8)> for Stick {
+    fn from((x, y): (u8, u8)) -> Stick {
+        Stick { x: x, y: y }
+    }
+}
+
+/// A position of the primary analog joystick that players aim for, such as the corners of the
+/// octagonal gate. See `Controller::is_near_notch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Notch {
+    /// The resting position.
+    Center,
+    /// Fully up.
+    Up,
+    /// Fully down.
+    Down,
+    /// Fully left.
+    Left,
+    /// Fully right.
+    Right,
+    /// The upper left corner of the gate.
+    UpLeft,
+    /// The upper right corner of the gate.
+    UpRight,
+    /// The lower left corner of the gate.
+    DownLeft,
+    /// The lower right corner of the gate.
+    DownRight,
+    /// An arbitrary position, given as an offset from the center.
+    Custom(i8, i8),
+}
+
+impl Notch {
+    /// Returns the position of the notch as an offset from the center, x-axis first.
+    ///
+    /// The gate positions assume a typical official controller, whose stick reaches about `80`
+    /// steps from the center along the axes and about `56` steps along both axes in the corners.
+    /// Individual controllers vary by a few steps, which the tolerance of
+    /// `Controller::is_near_notch` should account for.
+    pub fn offset(&self) -> (i8, i8) {
+        match *self {
+            Notch::Center => (0, 0),
+            Notch::Up => (0, 80),
+            Notch::Down => (0, -80),
+            Notch::Left => (-80, 0),
+            Notch::Right => (80, 0),
+            Notch::UpLeft => (-56, 56),
+            Notch::UpRight => (56, 56),
+            Notch::DownLeft => (-56, -56),
+            Notch::DownRight => (56, -56),
+            Notch::Custom(x, y) => (x, y),
+        }
+    }
+}
+
+/// One of eight directions of movement. See `Controller::movement_direction`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Up.
+    Up,
+    /// Up and to the right.
+    UpRight,
+    /// Right.
+    Right,
+    /// Down and to the right.
+    DownRight,
+    /// Down.
+    Down,
+    /// Down and to the left.
+    DownLeft,
+    /// Left.
+    Left,
+    /// Up and to the left.
+    UpLeft,
+}
+
+impl Direction {
+    // Returns the direction pointed to by the signs of `x` (positive being right) and `y`
+    // (positive being up), or `None` if both are zero.
+    fn from_signs(x: i8, y: i8) -> Option<Direction> {
+        match (x.signum(), y.signum()) {
+            (0, 1) => Some(Direction::Up),
+            (1, 1) => Some(Direction::UpRight),
+            (1, 0) => Some(Direction::Right),
+            (1, -1) => Some(Direction::DownRight),
+            (0, -1) => Some(Direction::Down),
+            (-1, -1) => Some(Direction::DownLeft),
+            (-1, 0) => Some(Direction::Left),
+            (-1, 1) => Some(Direction::UpLeft),
+            _ => None,
+        }
+    }
+}
+
+/// The level of depression of an analog trigger, `0` being completely up, `255` being completely
+/// pressed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Trigger(pub u8);
+
+impl Trigger {
+    /// Returns the level of depression mapped from `0..255` to `0.0..1.0`.
+    pub fn normalized(&self) -> f32 {
+        self.0 as f32 / 255.0
+    }
+}
+
+impl From<Trigger> for u8 {
+    fn from(trigger: Trigger) -> u8 {
+        trigger.0
+    }
+}
+
+impl From<u8> for Trigger {
+    fn from(value: u8) -> Trigger {
+        Trigger(value)
+    }
+}
+
+/// An analog input as named by SDL's game controller API. See `Controller::sdl_axis`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SdlAxis {
+    /// The x-axis of the primary analog joystick.
+    LeftX,
+    /// The y-axis of the primary analog joystick.
+    LeftY,
+    /// The x-axis of the secondary ("C") analog joystick.
+    RightX,
+    /// The y-axis of the secondary ("C") analog joystick.
+    RightY,
+    /// The analog "L" button.
+    TriggerLeft,
+    /// The analog "R" button.
+    TriggerRight,
+}
+
+fn clamp_u8(value: i16) -> u8 {
+    if value < 0 {
+        0
+    } else if value > 255 {
+        255
+    } else {
+        value as u8
+    }
+}
+
+// Maps a joystick axis centered at `127` onto the full `i16` range. See `Controller::to_i16_axes`.
+fn scale_axis(value: u8) -> i16 {
+    let offset = value as i32 - 127;
+    if offset <= 0 {
+        (offset * 32768 / 127) as i16
+    } else {
+        (offset * 32767 / 128) as i16
+    }
+}
+
+fn trigger_percent(value: u8) -> u8 {
+    (value as u16 * 100 / 255) as u8
+}
+
+fn stick_percent(value: u8) -> i8 {
+    cmp::min((value as i16 - 127) * 100 / 127, 100) as i8
+}
+
+/// The classification of a GameCube controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ControllerKind {
     /// The controller is wired and likely supports rumble.
     Wired,
@@ -291,6 +2803,32 @@ pub enum ControllerKind {
     Unknown,
 }
 
+impl ControllerKind {
+    /// Classifies a controller by the nibble found in the upper half of its port's status byte.
+    ///
+    /// `0` means that no controller is connected and results in `None`. `1` is a wired controller,
+    /// `2` is a wireless controller, and any other value is `ControllerKind::Unknown`.
+    pub fn from_nibble(nibble: u8) -> Option<ControllerKind> {
+        match nibble {
+            0 => None,
+            1 => Some(ControllerKind::Wired),
+            2 => Some(ControllerKind::Wireless),
+            _ => Some(ControllerKind::Unknown),
+        }
+    }
+
+    /// Returns the nibble that represents this classification in a status byte.
+    ///
+    /// `ControllerKind::Unknown` stands for every unrecognized nibble and is represented as `3`.
+    pub fn to_nibble(&self) -> u8 {
+        match *self {
+            ControllerKind::Wired => 1,
+            ControllerKind::Wireless => 2,
+            ControllerKind::Unknown => 3,
+        }
+    }
+}
+
 /// An error that occurs during usage of this library.
 #[derive(Debug)]
 pub enum Error {
@@ -298,8 +2836,81 @@ pub enum Error {
     Usb(libusb::Error),
     /// A seemingly valid adapter was found, but its communication protocol could not be resolved.
     UnrecognizedProtocol,
+    /// An operation required a controller on the given port, but none was connected.
+    PortEmpty(Port),
+    /// A port reported an unrecognized controller classification (the given status nibble) while
+    /// `Listener::set_strict_kind` was enabled.
+    UnknownControllerKind(Port, u8),
+    /// The adapter is already in use, either by another program or by a `Listener` that is still
+    /// alive.
+    DeviceBusy,
+    /// A message that does not start with the controller data report ID (`0x21`) was read from
+    /// the adapter. The report ID that was received is included.
+    UnexpectedReportId(u8),
+    /// A rumble command was issued to an adapter that has no output endpoint.
+    RumbleUnsupported,
     /// An invalid message was read from the adapter, likely due to a device or driver failure.
-    InvalidPacket,
+    ///
+    /// `read` is the number of bytes that actually arrived, which may be zero. Which lengths are
+    /// valid depends on `Listener::set_packet_len`, and is 37 bytes by default.
+    InvalidPacket {
+        /// The length of the message that was read.
+        read: usize,
+    },
+}
+
+impl Error {
+    /// Returns the category of this error.
+    ///
+    /// Unlike `Error` itself, which wraps `libusb::Error`, the returned value can be compared with
+    /// `==`. This is the stable way to classify errors: new `Error` variants may be added, but they
+    /// will map onto a matching `ErrorKind`. USB timeouts are split out from other USB errors
+    /// because they are routinely expected when polling.
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            Error::Usb(libusb::Error::Timeout) => ErrorKind::Timeout,
+            Error::Usb(_) => ErrorKind::Usb,
+            Error::UnrecognizedProtocol => ErrorKind::UnrecognizedProtocol,
+            Error::PortEmpty(_) => ErrorKind::PortEmpty,
+            Error::UnknownControllerKind(..) => ErrorKind::UnknownControllerKind,
+            Error::DeviceBusy => ErrorKind::DeviceBusy,
+            Error::UnexpectedReportId(_) => ErrorKind::UnexpectedReportId,
+            Error::RumbleUnsupported => ErrorKind::RumbleUnsupported,
+            Error::InvalidPacket { .. } => ErrorKind::InvalidPacket,
+        }
+    }
+
+    /// Classifies this error by the least drastic action likely to recover from it, so that
+    /// applications can implement a uniform escalation policy.
+    ///
+    /// | Error                                                   | Hint        |
+    /// |---------------------------------------------------------|-------------|
+    /// | `Usb(Timeout)`, `Usb(Interrupted)`, `Usb(Overflow)`     | `Retry`     |
+    /// | `InvalidPacket`, `UnexpectedReportId`                   | `Retry`     |
+    /// | `DeviceBusy`, `Usb(Busy)`                               | `Retry`     |
+    /// | `Usb(Pipe)`                                             | `Kick`      |
+    /// | `Usb(Io)`                                               | `Reset`     |
+    /// | `Usb(NoDevice)`, `Usb(NotFound)`                        | `Reconnect` |
+    /// | Any other error                                         | `Fatal`     |
+    ///
+    /// A busy adapter is treated as transient, matching `Adapter::listen_with_retry`: it is often
+    /// still being set up by the operating system, or about to be released by another program.
+    pub fn recovery_hint(&self) -> RecoveryHint {
+        match *self {
+            Error::Usb(libusb::Error::Timeout) |
+            Error::Usb(libusb::Error::Interrupted) |
+            Error::Usb(libusb::Error::Overflow) |
+            Error::InvalidPacket { .. } |
+            Error::UnexpectedReportId(_) |
+            Error::DeviceBusy |
+            Error::Usb(libusb::Error::Busy) => RecoveryHint::Retry,
+            Error::Usb(libusb::Error::Pipe) => RecoveryHint::Kick,
+            Error::Usb(libusb::Error::Io) => RecoveryHint::Reset,
+            Error::Usb(libusb::Error::NoDevice) |
+            Error::Usb(libusb::Error::NotFound) => RecoveryHint::Reconnect,
+            _ => RecoveryHint::Fatal,
+        }
+    }
 }
 
 impl StdError for Error {
@@ -307,7 +2918,12 @@ impl StdError for Error {
         match *self {
             Error::Usb(ref err) => err.description(),
             Error::UnrecognizedProtocol => "USB adapter protocol unrecognized",
-            Error::InvalidPacket => "Invalid data packet received",
+            Error::PortEmpty(_) => "No controller connected to port",
+            Error::UnknownControllerKind(..) => "Unknown controller kind reported",
+            Error::DeviceBusy => "USB adapter is in use",
+            Error::UnexpectedReportId(_) => "Unexpected data report received",
+            Error::RumbleUnsupported => "USB adapter does not support rumble",
+            Error::InvalidPacket { .. } => "Invalid data packet received",
         }
     }
 
@@ -323,13 +2939,770 @@ impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
         match *self {
             Error::Usb(ref err) => Display::fmt(err, f),
+            Error::PortEmpty(port) => write!(f, "{} {:?}", self.description(), port),
+            Error::UnknownControllerKind(port, nibble) => {
+                write!(f, "{} on port {:?} ({})", self.description(), port, nibble)
+            },
+            Error::UnexpectedReportId(id) => {
+                write!(f, "{} (got {:#04x}, expected {:#04x})", self.description(), id, REPORT_ID)
+            },
+            Error::DeviceBusy => {
+                write!(f, "{}; close any other software using the adapter", self.description())
+            },
+            Error::InvalidPacket { read } => {
+                write!(f, "{} (got {} bytes)", self.description(), read)
+            },
             _ => self.description().fmt(f),
         }
     }
 }
 
+/// The category of an `Error`, without any of its details. See `Error::kind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// `Error::Usb` with any error other than `libusb::Error::Timeout`.
+    Usb,
+    /// `Error::Usb(libusb::Error::Timeout)`.
+    Timeout,
+    /// `Error::UnrecognizedProtocol`.
+    UnrecognizedProtocol,
+    /// `Error::PortEmpty`.
+    PortEmpty,
+    /// `Error::UnknownControllerKind`.
+    UnknownControllerKind,
+    /// `Error::DeviceBusy`.
+    DeviceBusy,
+    /// `Error::UnexpectedReportId`.
+    UnexpectedReportId,
+    /// `Error::RumbleUnsupported`.
+    RumbleUnsupported,
+    /// `Error::InvalidPacket`.
+    InvalidPacket,
+}
+
+/// The least drastic action likely to recover from an `Error`. See `Error::recovery_hint`.
+///
+/// The variants are ordered by escalation: if an action does not help, the next one should be
+/// tried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RecoveryHint {
+    /// The error is transient; simply try the failed operation, such as `read` or `listen`,
+    /// again.
+    Retry,
+    /// The adapter stopped responding as expected; drop the `Listener` and call `Adapter::listen`
+    /// again to re-initialize it.
+    Kick,
+    /// The adapter is likely wedged; reset it with `Adapter::reset`.
+    Reset,
+    /// The adapter is gone; find it again with `Scanner::find_adapter`.
+    Reconnect,
+    /// The error cannot be recovered from automatically, for example due to missing permissions.
+    Fatal,
+}
+
+// Converts errors signaling that another process (or another handle in this process) holds the
+// device. Linux reports this as `Busy` when claiming the interface, whereas Windows denies access
+// to the device outright.
+fn busy_error(err: libusb::Error) -> Error {
+    match err {
+        libusb::Error::Busy => Error::DeviceBusy,
+        #[cfg(windows)]
+        libusb::Error::Access => Error::DeviceBusy,
+        err => Error::Usb(err),
+    }
+}
+
 impl From<libusb::Error> for Error {
     fn from(err: libusb::Error) -> Error {
         Error::Usb(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet() -> [u8; PACKET_LEN] {
+        let mut data = [0; PACKET_LEN];
+        data[0] = REPORT_ID;
+        data
+    }
+
+    #[test]
+    fn validate_packet_rejects_short_reads() {
+        let data = packet();
+        for &read in [0, 8].iter() {
+            match validate_packet(PacketLen::default(), read, &data) {
+                Err(Error::InvalidPacket { read: got }) => assert_eq!(got, read),
+                other => panic!("unexpected result for {} bytes: {:?}", read, other),
+            }
+        }
+    }
+
+    #[test]
+    fn validate_packet_length_modes() {
+        let mut data = [0; 64];
+        data[0] = REPORT_ID;
+
+        match validate_packet(PacketLen::Fixed(PACKET_LEN), 64, &data) {
+            Err(Error::InvalidPacket { read: 64 }) => {},
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(validate_packet(PacketLen::Auto, 64, &data).is_ok());
+        assert!(validate_packet(PacketLen::Fixed(64), 64, &data).is_ok());
+
+        match validate_packet(PacketLen::Fixed(20), 20, &data) {
+            Err(Error::InvalidPacket { read: 20 }) => {},
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_packet_checks_report_id() {
+        let mut data = packet();
+        assert!(validate_packet(PacketLen::default(), PACKET_LEN, &data).is_ok());
+
+        data[0] = 0x22;
+        match validate_packet(PacketLen::default(), PACKET_LEN, &data) {
+            Err(Error::UnexpectedReportId(0x22)) => {},
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn short_packet_error_kind() {
+        let err = validate_packet(PacketLen::default(), 8, &packet()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidPacket);
+    }
+
+    fn neutral() -> Controller {
+        ControllerBuilder::new().build()
+    }
+
+    #[test]
+    fn to_i16_axes_extremes() {
+        let mut controller = neutral();
+        controller.l_analog = 255;
+        let axes = controller.to_i16_axes();
+        assert_eq!(axes.stick_x, 0);
+        assert_eq!(axes.c_stick_y, 0);
+        assert_eq!(axes.l_analog, 65535);
+        assert_eq!(axes.r_analog, 0);
+
+        controller.stick_x = 0;
+        controller.stick_y = 255;
+        let axes = controller.to_i16_axes();
+        assert_eq!(axes.stick_x, -32768);
+        assert_eq!(axes.stick_y, 32767);
+    }
+
+    #[test]
+    fn sdl_axis_extremes() {
+        let mut controller = neutral();
+        assert_eq!(controller.sdl_axis(SdlAxis::LeftY), 0);
+        assert_eq!(controller.sdl_axis(SdlAxis::TriggerRight), 0);
+
+        controller.stick_x = 255;
+        controller.stick_y = 255;
+        controller.c_stick_x = 0;
+        controller.c_stick_y = 0;
+        controller.l_analog = 255;
+        assert_eq!(controller.sdl_axis(SdlAxis::LeftX), 32767);
+        assert_eq!(controller.sdl_axis(SdlAxis::LeftY), -32767);
+        assert_eq!(controller.sdl_axis(SdlAxis::RightX), -32768);
+        assert_eq!(controller.sdl_axis(SdlAxis::RightY), 32767);
+        assert_eq!(controller.sdl_axis(SdlAxis::TriggerLeft), 32767);
+    }
+
+    #[test]
+    fn connected_ports() {
+        let none = Controllers([None; 4]);
+        assert!(!none.any_connected());
+        assert!(!none.all_connected());
+
+        let one = Controllers([None, Some(neutral()), None, None]);
+        assert!(one.any_connected());
+        assert!(!one.all_connected());
+
+        let four = Controllers([Some(neutral()); 4]);
+        assert!(four.any_connected());
+        assert!(four.all_connected());
+    }
+
+    #[test]
+    fn into_iter_yields_ports_in_order() {
+        let controllers = Controllers([None, Some(neutral()), None, Some(neutral())]);
+        let expected = vec![
+            (Port::One, false),
+            (Port::Two, true),
+            (Port::Three, false),
+            (Port::Four, true),
+        ];
+
+        let by_ref: Vec<_> = (&controllers).into_iter().map(|(p, c)| (p, c.is_some())).collect();
+        assert_eq!(by_ref, expected);
+        let by_value: Vec<_> = controllers.into_iter().map(|(p, c)| (p, c.is_some())).collect();
+        assert_eq!(by_value, expected);
+    }
+
+    #[test]
+    fn mirror_swaps_left_and_right() {
+        let controller = ControllerBuilder::new()
+            .left(true)
+            .l(true)
+            .l_analog(200)
+            .up(true)
+            .a(true)
+            .stick(0, 40)
+            .c_stick(200, 60)
+            .build();
+        let mirrored = controller.mirror();
+
+        assert!(!mirrored.left && mirrored.right);
+        assert!(!mirrored.l && mirrored.r);
+        assert_eq!((mirrored.l_analog, mirrored.r_analog), (0, 200));
+        assert_eq!((mirrored.stick_x, mirrored.stick_y), (254, 40));
+        assert_eq!((mirrored.c_stick_x, mirrored.c_stick_y), (54, 60));
+        assert!(mirrored.up && !mirrored.down);
+        assert!(mirrored.a && !mirrored.b);
+    }
+
+    #[test]
+    fn port_kinds_wired_on_port_one() {
+        let mut data = packet();
+        data[1] = 0x10;
+        assert_eq!(Controller::port_kinds(&data), [Some(ControllerKind::Wired), None, None, None]);
+    }
+
+    #[test]
+    fn to_json_neutral() {
+        assert_eq!(neutral().to_json(), concat!(
+            "{\"kind\":\"Wired\",\"a\":false,\"b\":false,\"x\":false,\"y\":false,\"up\":false,",
+            "\"down\":false,\"left\":false,\"right\":false,\"l\":false,\"r\":false,",
+            "\"l_analog\":0,\"r_analog\":0,\"z\":false,\"start\":false,\"stick_x\":127,",
+            "\"stick_y\":127,\"c_stick_x\":127,\"c_stick_y\":127}",
+        ));
+    }
+
+    #[test]
+    fn start_pressed_across_ports() {
+        let start = ControllerBuilder::new().start(true).build();
+
+        let port_two = Controllers([Some(neutral()), Some(start), None, None]);
+        assert!(port_two.any_pressed(Button::Start));
+        assert!(!port_two.all_pressed(Button::Start));
+
+        let all = Controllers([Some(start), None, Some(start), None]);
+        assert!(all.any_pressed(Button::Start));
+        assert!(all.all_pressed(Button::Start));
+    }
+
+    #[test]
+    fn malformed_lengths_do_not_panic() {
+        let data = [REPORT_ID; PACKET_LEN + 1];
+        for len in 0..PACKET_LEN {
+            assert_eq!(Controller::iter_packet(&data[..len]).count(), 0);
+            assert!(Controllers::unpack(&data[..len]).is_err());
+        }
+        assert_eq!(Controller::iter_packet(&data).count(), 0);
+        assert!(Controllers::unpack(&data).is_err());
+    }
+
+    #[test]
+    fn activity_range() {
+        assert!(neutral().activity().abs() < 1e-6);
+
+        let engaged = ControllerBuilder::new()
+            .a(true).b(true).x(true).y(true)
+            .up(true).down(true).left(true).right(true)
+            .l(true).r(true).z(true).start(true)
+            .l_analog(255)
+            .r_analog(255)
+            .stick(255, 255)
+            .c_stick(0, 0)
+            .build();
+        assert!((engaged.activity() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clamp_analog_band() {
+        let controller = ControllerBuilder::new()
+            .stick(5, 250)
+            .c_stick(127, 30)
+            .l_analog(5)
+            .r_analog(250)
+            .a(true)
+            .build();
+        let clamped = controller.clamp_analog(30, 225);
+
+        assert_eq!((clamped.stick_x, clamped.stick_y), (30, 225));
+        assert_eq!((clamped.c_stick_x, clamped.c_stick_y), (127, 30));
+        assert_eq!((clamped.l_analog, clamped.r_analog), (30, 225));
+        assert!(clamped.a);
+    }
+
+    #[test]
+    fn checksum_tracks_frame_contents() {
+        let frame = Controllers([Some(neutral()), None, None, None]);
+        let same = Controllers([Some(neutral()), None, None, None]);
+        assert_eq!(frame.checksum(), same.checksum());
+
+        let a = ControllerBuilder::new().a(true).build();
+        let pressed = Controllers([Some(a), None, None, None]);
+        assert!(frame.checksum() != pressed.checksum());
+        let moved = Controllers([None, Some(neutral()), None, None]);
+        assert!(frame.checksum() != moved.checksum());
+    }
+
+    #[test]
+    fn kind_nibble_round_trip() {
+        for nibble in 1..4 {
+            let kind = ControllerKind::from_nibble(nibble).unwrap();
+            assert_eq!(kind.to_nibble(), nibble);
+        }
+        assert_eq!(ControllerKind::from_nibble(0), None);
+    }
+
+    #[test]
+    fn diff_press_and_disconnect() {
+        let previous = Controllers([Some(neutral()), Some(neutral()), None, None]);
+        let a = ControllerBuilder::new().a(true).build();
+        let current = Controllers([Some(a), None, None, None]);
+        assert_eq!(current.diff(&previous, 0), vec![
+            (Port::One, InputEvent::ButtonDown(Button::A)),
+            (Port::Two, InputEvent::Disconnected),
+        ]);
+    }
+
+    #[test]
+    fn describe_changes_strings() {
+        let previous = ControllerBuilder::new().start(true).stick(120, 127).build();
+        let current = ControllerBuilder::new().a(true).stick(200, 127).build();
+        assert_eq!(current.describe_changes(&previous), vec![
+            "A pressed".to_string(),
+            "Start released".to_string(),
+            "stick_x 120 -> 200".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn array_conversion_round_trip() {
+        let frame = [None, Some(ControllerBuilder::new().b(true).build()), None, None];
+        let controllers = Controllers::from(frame);
+        assert!(controllers.as_array()[1].unwrap().b);
+
+        let array: [Option<Controller>; 4] = controllers.into();
+        assert_eq!(array.iter().map(Option::is_some).collect::<Vec<_>>(),
+                   vec![false, true, false, false]);
+        assert!(array[1].unwrap().b);
+    }
+
+    #[test]
+    fn button_index_round_trip() {
+        for index in 0..12 {
+            assert_eq!(Button::from_index(index).unwrap().index(), index);
+        }
+        assert_eq!(Button::from_index(12), None);
+    }
+
+    #[test]
+    fn csv_row_matches_header() {
+        let columns = Controller::CSV_HEADER.split(',').count();
+        assert_eq!(columns, 19);
+        assert_eq!(neutral().to_csv_row().split(',').count(), columns);
+        assert!(neutral().to_csv_row().starts_with("Wired,0,"));
+    }
+
+    #[test]
+    fn stick_and_trigger_conversions() {
+        let controller = ControllerBuilder::new()
+            .stick(0, 255)
+            .c_stick(127, 30)
+            .r_analog(255)
+            .build();
+        assert_eq!(controller.stick(), Stick { x: 0, y: 255 });
+        assert_eq!(controller.c_stick(), Stick::from((127, 30)));
+        assert_eq!(<(u8, u8)>::from(controller.c_stick()), (127, 30));
+        assert_eq!(controller.stick().normalized(), (-1.0, 1.0));
+        assert_eq!(controller.c_stick().normalized().0, 0.0);
+
+        assert_eq!(controller.l_trigger(), Trigger(0));
+        assert_eq!(u8::from(controller.r_trigger()), 255);
+        assert_eq!(Trigger::from(51).normalized(), 0.2);
+    }
+
+    #[test]
+    fn lerp_endpoints_and_midpoint() {
+        let a = ControllerBuilder::new().a(true).stick(0, 100).l_analog(0).build();
+        let b = ControllerBuilder::new().b(true).stick(200, 101).l_analog(255).build();
+
+        let start = Controller::lerp(&a, &b, 0.0);
+        assert!(start.a && !start.b);
+        assert_eq!((start.stick_x, start.stick_y, start.l_analog), (0, 100, 0));
+
+        let middle = Controller::lerp(&a, &b, 0.5);
+        assert!(!middle.a && middle.b);
+        assert_eq!((middle.stick_x, middle.stick_y, middle.l_analog), (100, 101, 128));
+
+        let end = Controller::lerp(&a, &b, 1.0);
+        assert!(!end.a && end.b);
+        assert_eq!((end.stick_x, end.stick_y, end.l_analog), (200, 101, 255));
+    }
+
+    #[test]
+    fn status_line_two_connected() {
+        let wireless = ControllerBuilder::new().kind(ControllerKind::Wireless).build();
+        let controllers = Controllers([Some(neutral()), None, Some(wireless), None]);
+        assert_eq!(controllers.status_line(), "[Wired][--][Wireless][--]");
+    }
+
+    fn holding(button: Button) -> Controller {
+        let builder = ControllerBuilder::new();
+        match button {
+            Button::A => builder.a(true),
+            Button::B => builder.b(true),
+            Button::X => builder.x(true),
+            Button::Y => builder.y(true),
+            Button::Start => builder.start(true),
+            Button::Z => builder.z(true),
+            Button::L => builder.l(true),
+            Button::R => builder.r(true),
+            Button::Up => builder.up(true),
+            Button::Down => builder.down(true),
+            Button::Left => builder.left(true),
+            Button::Right => builder.right(true),
+        }.build()
+    }
+
+    #[test]
+    fn standard_mapping() {
+        let mapping = [
+            (StandardButton::South, Button::A),
+            (StandardButton::East, Button::B),
+            (StandardButton::West, Button::Y),
+            (StandardButton::North, Button::X),
+            (StandardButton::RightShoulder, Button::Z),
+            (StandardButton::LeftTrigger, Button::L),
+            (StandardButton::RightTrigger, Button::R),
+            (StandardButton::Start, Button::Start),
+            (StandardButton::DPadUp, Button::Up),
+            (StandardButton::DPadDown, Button::Down),
+            (StandardButton::DPadLeft, Button::Left),
+            (StandardButton::DPadRight, Button::Right),
+        ];
+
+        for &(standard, button) in mapping.iter() {
+            let controller = holding(button);
+            for &(other, _) in mapping.iter() {
+                assert_eq!(controller.standard(other), other == standard);
+            }
+            assert!(!controller.standard(StandardButton::LeftShoulder));
+        }
+    }
+
+    #[test]
+    fn empty_frames() {
+        assert!(Controllers([None; 4]).is_empty());
+        assert!(!Controllers([None, None, None, Some(neutral())]).is_empty());
+    }
+
+    #[test]
+    fn iter_packet_valid() {
+        let mut data = packet();
+        data[10] = 0x20;
+        let ports: Vec<_> = Controller::iter_packet(&data).map(|c| c.map(|c| c.kind)).collect();
+        assert_eq!(ports, vec![None, Some(ControllerKind::Wireless), None, None]);
+    }
+
+    #[test]
+    fn percentages() {
+        let low = ControllerBuilder::new().stick(0, 0).c_stick(0, 0).build();
+        assert_eq!((low.stick_x_percent(), low.c_stick_y_percent()), (-100, -100));
+        assert_eq!((low.l_analog_percent(), low.r_analog_percent()), (0, 0));
+
+        let center = ControllerBuilder::new().l_analog(127).build();
+        assert_eq!((center.stick_y_percent(), center.c_stick_x_percent()), (0, 0));
+        assert_eq!(center.l_analog_percent(), 49);
+
+        let high = ControllerBuilder::new().stick(255, 255).l_analog(255).r_analog(255).build();
+        assert_eq!((high.stick_x_percent(), high.stick_y_percent()), (100, 100));
+        assert_eq!((high.l_analog_percent(), high.r_analog_percent()), (100, 100));
+    }
+
+    #[test]
+    fn near_notches() {
+        let up = ControllerBuilder::new().stick(128, 205).build();
+        assert!(up.is_near_notch(Notch::Up, 3));
+        assert!(!up.is_near_notch(Notch::Up, 1));
+        assert!(!up.is_near_notch(Notch::UpRight, 3));
+
+        let down_left = ControllerBuilder::new().stick(71, 73).build();
+        assert!(down_left.is_near_notch(Notch::DownLeft, 2));
+        assert!(!down_left.is_near_notch(Notch::Down, 2));
+        assert!(!down_left.is_near_notch(Notch::Left, 2));
+    }
+
+    #[test]
+    fn merge_two_frames() {
+        let first = ControllerBuilder::new().a(true).stick(200, 120).l_analog(40).build();
+        let second = ControllerBuilder::new().b(true).stick(100, 20).l_analog(30).build();
+        let a = Controllers([Some(first), Some(first), None, None]);
+        let b = Controllers([Some(second), None, Some(second), None]);
+        let merged = a.merge(&b);
+
+        let both = merged.0[0].unwrap();
+        assert!(both.a && both.b && !both.x);
+        assert_eq!((both.stick_x, both.stick_y), (200, 20));
+        assert_eq!(both.l_analog, 40);
+        assert!(merged.0[1].unwrap().a);
+        assert!(merged.0[2].unwrap().b);
+        assert!(merged.0[3].is_none());
+    }
+
+    #[test]
+    fn implausible_states() {
+        assert!(neutral().is_plausible());
+        assert!(ControllerBuilder::new().a(true).up(true).right(true).build().is_plausible());
+
+        let all_held = Controller::from_raw(1, 0x0fff, [127; 4], [0; 2]).unwrap();
+        assert!(Button::ALL.iter().all(|&button| all_held.is_pressed(button)));
+        assert!(!all_held.is_plausible());
+        assert!(!ControllerBuilder::new().up(true).down(true).build().is_plausible());
+        assert!(!ControllerBuilder::new().left(true).right(true).build().is_plausible());
+    }
+
+    #[test]
+    fn initial_events_report_held_buttons() {
+        let held = ControllerBuilder::new().a(true).build();
+        let controllers = Controllers([None, Some(held), None, None]);
+        assert_eq!(controllers.initial_events(0), vec![
+            (Port::Two, InputEvent::Connected(ControllerKind::Wired)),
+            (Port::Two, InputEvent::ButtonDown(Button::A)),
+        ]);
+        assert!(controllers.diff(&controllers, 0).is_empty());
+    }
+
+    #[test]
+    fn reset_combo() {
+        let combo = ControllerBuilder::new().a(true).b(true).x(true).y(true).start(true);
+        assert!(combo.build().is_reset_combo());
+        assert!(combo.z(true).build().is_reset_combo());
+        assert!(!combo.x(false).build().is_reset_combo());
+    }
+
+    #[test]
+    fn movement_directions() {
+        let stick = ControllerBuilder::new().stick(200, 210).build();
+        assert_eq!(stick.movement_direction(0.2), Some(Direction::UpRight));
+        assert_eq!(ControllerBuilder::new().stick(140, 120).build().movement_direction(0.2), None);
+
+        let pad = ControllerBuilder::new().down(true).left(true).build();
+        assert_eq!(pad.movement_direction(0.2), Some(Direction::DownLeft));
+
+        let conflicting = ControllerBuilder::new().left(true).right(true).stick(20, 127).build();
+        assert_eq!(conflicting.movement_direction(0.2), Some(Direction::Left));
+        let pad_wins = ControllerBuilder::new().up(true).stick(20, 127).build();
+        assert_eq!(pad_wins.movement_direction(0.2), Some(Direction::Up));
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        let wireless = ControllerBuilder::new()
+            .kind(ControllerKind::Wireless)
+            .x(true)
+            .l(true)
+            .stick(12, 240)
+            .r_analog(99)
+            .build();
+        let frame = Controllers([None, Some(neutral()), None, Some(wireless)]);
+        let packed = frame.pack();
+        assert_eq!(packed.len(), PACKET_LEN);
+
+        let unpacked = Controllers::unpack(&packed).unwrap();
+        assert_eq!(unpacked.kinds(), frame.kinds());
+        let port_four = unpacked.0[3].unwrap();
+        assert!(port_four.x && port_four.l && !port_four.a);
+        assert_eq!((port_four.stick_x, port_four.stick_y, port_four.r_analog), (12, 240, 99));
+        assert_eq!(unpacked.pack(), packed);
+    }
+
+    #[test]
+    fn signed_accessors() {
+        let low = ControllerBuilder::new().stick(0, 0).c_stick(0, 0).build();
+        assert_eq!((low.stick_x_signed(), low.stick_y_signed()), (-127, -127));
+        assert_eq!((low.c_stick_x_signed(), low.c_stick_y_signed()), (-127, -127));
+
+        let center = neutral();
+        assert_eq!((center.stick_x_signed(), center.stick_y_signed()), (0, 0));
+        assert_eq!((center.c_stick_x_signed(), center.c_stick_y_signed()), (0, 0));
+
+        let high = ControllerBuilder::new().stick(255, 255).c_stick(255, 255).build();
+        assert_eq!((high.stick_x_signed(), high.stick_y_signed()), (128, 128));
+        assert_eq!((high.c_stick_x_signed(), high.c_stick_y_signed()), (128, 128));
+    }
+
+    #[test]
+    fn count_mixed_kinds() {
+        let wireless = ControllerBuilder::new().kind(ControllerKind::Wireless).build();
+        let unknown = ControllerBuilder::new().kind(ControllerKind::Unknown).build();
+        let controllers =
+            Controllers([Some(wireless), Some(neutral()), Some(unknown), Some(wireless)]);
+        assert_eq!(controllers.count_by_kind(), (1, 2, 1));
+        assert_eq!(Controllers([None; 4]).count_by_kind(), (0, 0, 0));
+    }
+
+    #[test]
+    fn parse_port_matches_full_parse() {
+        let mut data = packet();
+        data[1] = 0x10;
+        data[19..28].copy_from_slice(&[0x20, 0x81, 0x02, 30, 220, 127, 90, 12, 250]);
+
+        let full = Controller::parse_packet(&data);
+        let port = Controller::parse_port(&data, Port::Three).unwrap();
+        assert_eq!(port.to_block(), full[2].unwrap().to_block());
+        assert!(port.a && port.up && port.z);
+        assert!(Controller::parse_port(&data, Port::Four).is_none());
+    }
+
+    #[test]
+    fn frame_pair_rotates() {
+        let first = [Some(neutral()), None, None, None];
+        let second = [None, Some(neutral()), None, None];
+        let mut pair = FramePair { previous: [None; 4], current: [None; 4] };
+
+        pair.push(first);
+        assert_eq!(Controllers(pair.previous).kinds(), [None; 4]);
+        assert_eq!(Controllers(pair.current).kinds(), Controllers(first).kinds());
+
+        pair.push(second);
+        assert_eq!(Controllers(pair.previous).kinds(), Controllers(first).kinds());
+        assert_eq!(Controllers(pair.current).kinds(), Controllers(second).kinds());
+    }
+
+    #[test]
+    fn check_kinds_rejects_unknown_nibbles() {
+        let mut data = packet();
+        data[1] = 0x10;
+        data[10] = 0x24;
+        assert!(check_kinds(&data).is_ok());
+
+        data[28] = 0x50;
+        match check_kinds(&data) {
+            Err(Error::UnknownControllerKind(Port::Four, 5)) => {},
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bytes_read_counts_accepted_packets_only() {
+        let mut log = PacketLog::default();
+        let mut padded = [0; 64];
+        padded[0] = REPORT_ID;
+
+        assert!(log.accept(PacketLen::default(), false, PACKET_LEN, &packet()).is_ok());
+        assert!(log.accept(PacketLen::Auto, false, 64, &padded).is_ok());
+        assert_eq!(log.bytes_read, 101);
+
+        assert!(log.accept(PacketLen::default(), false, 8, &packet()).is_err());
+        let mut unknown = packet();
+        unknown[1] = 0x50;
+        assert!(log.accept(PacketLen::default(), true, PACKET_LEN, &unknown).is_err());
+        assert_eq!(log.bytes_read, 101);
+
+        assert!(log.accept(PacketLen::default(), false, PACKET_LEN, &unknown).is_ok());
+        assert_eq!(log.bytes_read, 138);
+    }
+
+    #[test]
+    fn invert_triggers_flips_analog_values() {
+        let pressed = ControllerBuilder::new().l_analog(255).r_analog(40).stick(10, 20).build();
+        let mut controllers = [Some(pressed), None, Some(neutral()), None];
+        invert_triggers(&mut controllers);
+
+        let inverted = controllers[0].unwrap();
+        assert_eq!((inverted.l_analog, inverted.r_analog), (0, 215));
+        assert_eq!((inverted.stick_x, inverted.stick_y), (10, 20));
+        assert_eq!(controllers[2].unwrap().l_analog, 255);
+        assert!(controllers[1].is_none());
+    }
+
+    #[test]
+    fn synthesize_trigger_clicks_above_threshold() {
+        let worn = ControllerBuilder::new().l_analog(250).r_analog(200).build();
+
+        let mut controllers = [Some(worn), None, None, None];
+        synthesize_trigger_clicks(&mut controllers, None);
+        assert!(!controllers[0].unwrap().l && !controllers[0].unwrap().r);
+
+        synthesize_trigger_clicks(&mut controllers, Some(200));
+        let clicked = controllers[0].unwrap();
+        assert!(clicked.l);
+        assert!(!clicked.r);
+        assert!(controllers[1].is_none());
+
+        let mut controllers = [Some(worn), None, None, None];
+        synthesize_trigger_clicks(&mut controllers, Some(199));
+        assert!(controllers[0].unwrap().l && controllers[0].unwrap().r);
+    }
+
+    #[test]
+    fn capture_record_format() {
+        let micros = || SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_micros() as u64;
+        let data = [REPORT_ID, 1, 2, 3];
+        let mut record = Vec::new();
+
+        let before = micros();
+        write_capture_record(&mut record, &data).unwrap();
+        let after = micros();
+
+        assert_eq!(record.len(), 8 + 2 + data.len());
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&record[..8]);
+        let timestamp = u64::from_le_bytes(timestamp);
+        assert!(before <= timestamp && timestamp <= after);
+        assert_eq!(&record[8..10], &[4, 0]);
+        assert_eq!(&record[10..], &data);
+    }
+
+    #[test]
+    fn repeats_compare_accepted_packets() {
+        let mut log = PacketLog::default();
+        let mut changed = packet();
+        changed[2] = 0x01;
+
+        log.accept(PacketLen::default(), false, PACKET_LEN, &packet()).unwrap();
+        assert!(!log.repeated);
+        log.accept(PacketLen::default(), false, PACKET_LEN, &packet()).unwrap();
+        assert!(log.repeated);
+        log.accept(PacketLen::default(), false, PACKET_LEN, &changed).unwrap();
+        assert!(!log.repeated);
+
+        // A rejected packet leaves the comparison to the last accepted one.
+        let mut unknown = changed;
+        unknown[1] = 0x50;
+        assert!(log.accept(PacketLen::default(), true, PACKET_LEN, &unknown).is_err());
+        log.accept(PacketLen::default(), true, PACKET_LEN, &changed).unwrap();
+        assert!(log.repeated);
+    }
+
+    #[cfg(feature = "rumble")]
+    #[test]
+    fn rumble_sent_only_when_changed_or_stale() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let on = [1, 0, 0, 0];
+        let last = Some((on, start));
+
+        assert!(should_send_rumble(on, None, None, start));
+
+        // Unchanged: repeated calls within the refresh interval write nothing.
+        assert!(!should_send_rumble(on, last, None, start + ms(5000)));
+        assert!(!should_send_rumble(on, last, Some(ms(500)), start + ms(499)));
+
+        // Changed.
+        assert!(should_send_rumble([0; 4], last, Some(ms(500)), start + ms(1)));
+
+        // Stale.
+        assert!(should_send_rumble(on, last, Some(ms(500)), start + ms(500)));
+    }
+}
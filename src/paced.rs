@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+use {Controller, Error, Listener};
+
+/// Limits how often a `Listener` is actually read, for integration into fixed-timestep loops.
+///
+/// `poll` only reads from the adapter once at least the configured interval has passed since the
+/// previous read. In between, it immediately returns the states from that read, so the loop's
+/// frame rate is decoupled from the rate at which the adapter sends packets.
+pub struct PacedReader<'a> {
+    listener: Listener<'a>,
+    interval: Duration,
+    last: Option<(Instant, [Option<Controller>; 4])>,
+}
+
+impl<'a> PacedReader<'a> {
+    /// Wraps a `Listener`, reading from it at most once every `interval`. The first call to `poll`
+    /// always reads.
+    pub fn new(listener: Listener<'a>, interval: Duration) -> PacedReader<'a> {
+        PacedReader {
+            listener: listener,
+            interval: interval,
+            last: None,
+        }
+    }
+
+    /// Returns the states of all four ports, reading from the adapter only if the interval has
+    /// elapsed since the last read.
+    ///
+    /// Reads are performed with `Listener::read_latest`: the adapter keeps sending packets while
+    /// no read takes place, so the ones that queued up since the last read are flushed and the
+    /// freshest is returned. A read waits for a packet like `Listener::read` does, which normally
+    /// takes no longer than the adapter's own polling interval.
+    ///
+    /// If a read fails, the error is returned and the next call reads again.
+    pub fn poll(&mut self) -> Result<[Option<Controller>; 4], Error> {
+        if let Some((at, controllers)) = self.last {
+            if !is_read_due(at, self.interval, Instant::now()) {
+                return Ok(controllers);
+            }
+        }
+
+        let controllers = try!(self.listener.read_latest());
+        self.last = Some((Instant::now(), controllers));
+        Ok(controllers)
+    }
+
+    /// Changes the minimum time between reads.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Returns the wrapped `Listener`.
+    pub fn into_inner(self) -> Listener<'a> {
+        self.listener
+    }
+}
+
+// Returns `true` if, as of `now`, at least `interval` has passed since the read at `last`. A `now`
+// that precedes `last` counts as no time having passed.
+fn is_read_due(last: Instant, interval: Duration, now: Instant) -> bool {
+    now.checked_duration_since(last).unwrap_or(Duration::from_secs(0)) >= interval
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_only_at_interval() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let interval = ms(16);
+
+        // Poll every 5 ms, starting a new interval whenever a read is due.
+        let mut last = start;
+        let mut reads = Vec::new();
+        for step in 1..10 {
+            let now = start + ms(step * 5);
+            if is_read_due(last, interval, now) {
+                reads.push(step * 5);
+                last = now;
+            }
+        }
+        assert_eq!(reads, vec![20, 40]);
+
+        assert!(is_read_due(start, interval, start + interval));
+        assert!(!is_read_due(start + ms(1), interval, start));
+    }
+}
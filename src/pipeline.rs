@@ -0,0 +1,114 @@
+use {Controller, Controllers};
+
+/// A step that post-processes every frame of controller states, such as a deadzone or a remapping.
+///
+/// Any `FnMut(&mut [Option<Controller>; 4])` closure is a processor.
+pub trait FrameProcessor {
+    /// Modifies a frame in place.
+    fn process(&mut self, frame: &mut [Option<Controller>; 4]);
+}
+
+impl<F> FrameProcessor for F where F: FnMut(&mut [Option<Controller>; 4]) {
+    fn process(&mut self, frame: &mut [Option<Controller>; 4]) {
+        self(frame)
+    }
+}
+
+/// An ordered chain of `FrameProcessor`s.
+pub struct FramePipeline {
+    processors: Vec<Box<FrameProcessor>>,
+}
+
+impl FramePipeline {
+    /// Returns an empty pipeline, which leaves frames untouched.
+    pub fn new() -> FramePipeline {
+        FramePipeline { processors: Vec::new() }
+    }
+
+    /// Appends a processor to the end of the pipeline and returns the pipeline, for chaining.
+    pub fn with<P: FrameProcessor + 'static>(mut self, processor: P) -> FramePipeline {
+        self.push(processor);
+        self
+    }
+
+    /// Appends a processor to the end of the pipeline.
+    pub fn push<P: FrameProcessor + 'static>(&mut self, processor: P) {
+        self.processors.push(Box::new(processor));
+    }
+
+    /// Runs every processor on `frame`, in the order they were added.
+    ///
+    /// A frame in which every port is empty is left as it is without running any processor, so
+    /// polling with nothing plugged in costs next to nothing.
+    pub fn apply(&mut self, frame: &mut [Option<Controller>; 4]) {
+        if Controllers(*frame).is_empty() {
+            return;
+        }
+
+        for processor in &mut self.processors {
+            processor.process(frame);
+        }
+    }
+}
+
+impl Default for FramePipeline {
+    fn default() -> FramePipeline {
+        FramePipeline::new()
+    }
+}
+
+/// A processor that applies `Controller::clamp_analog` to every connected controller.
+#[derive(Clone, Copy, Debug)]
+pub struct ClampAnalog {
+    /// The lowest analog value to let through.
+    pub min: u8,
+    /// The highest analog value to let through.
+    pub max: u8,
+}
+
+impl FrameProcessor for ClampAnalog {
+    fn process(&mut self, frame: &mut [Option<Controller>; 4]) {
+        for controller in frame.iter_mut() {
+            if let Some(ref mut controller) = *controller {
+                *controller = controller.clamp_analog(self.min, self.max);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use super::*;
+    use ControllerBuilder;
+
+    #[test]
+    fn processors_run_in_order() {
+        let mut pipeline = FramePipeline::new()
+            .with(|frame: &mut [Option<Controller>; 4]| {
+                frame[0].as_mut().unwrap().stick_x = 10;
+            })
+            .with(|frame: &mut [Option<Controller>; 4]| {
+                frame[0].as_mut().unwrap().stick_x *= 2;
+            });
+
+        let mut frame = [Some(ControllerBuilder::new().build()), None, None, None];
+        pipeline.apply(&mut frame);
+        assert_eq!(frame[0].unwrap().stick_x, 20);
+    }
+
+    #[test]
+    fn empty_frames_skip_processors() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let mut pipeline = FramePipeline::new()
+            .with(move |_: &mut [Option<Controller>; 4]| counter.set(counter.get() + 1));
+
+        pipeline.apply(&mut [None; 4]);
+        assert_eq!(calls.get(), 0);
+
+        pipeline.apply(&mut [None, None, Some(ControllerBuilder::new().build()), None]);
+        assert_eq!(calls.get(), 1);
+    }
+}
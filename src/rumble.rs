@@ -0,0 +1,190 @@
+use std::time::Duration;
+use Port;
+
+/// A rumble effect made of consecutive segments during which the motor is either on or off.
+///
+/// For example, "two short buzzes" is `on 100 ms, off 100 ms, on 100 ms`.
+#[derive(Clone, Debug)]
+pub struct RumblePattern {
+    segments: Vec<(bool, Duration)>,
+    looping: bool,
+}
+
+impl RumblePattern {
+    /// Returns a pattern that plays its segments once, after which the motor stays off.
+    pub fn once(segments: Vec<(bool, Duration)>) -> RumblePattern {
+        RumblePattern {
+            segments: segments,
+            looping: false,
+        }
+    }
+
+    /// Returns a pattern that repeats its segments indefinitely.
+    pub fn looping(segments: Vec<(bool, Duration)>) -> RumblePattern {
+        RumblePattern {
+            segments: segments,
+            looping: true,
+        }
+    }
+
+    /// Returns `true` if the pattern repeats indefinitely.
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Returns the total length of one pass through the segments.
+    pub fn duration(&self) -> Duration {
+        self.segments.iter().fold(Duration::from_secs(0), |total, &(_, length)| total + length)
+    }
+
+    /// Returns `true` if the pattern is over after `elapsed` has passed since it started. Looping
+    /// patterns are never over.
+    pub fn is_finished(&self, elapsed: Duration) -> bool {
+        !self.looping && elapsed >= self.duration()
+    }
+
+    /// Returns whether the motor should be on once `elapsed` has passed since the pattern
+    /// started.
+    ///
+    /// Segment boundaries belong to the later segment. A finished one-shot pattern, as well as a
+    /// pattern with no length at all, is off.
+    pub fn state_at(&self, elapsed: Duration) -> bool {
+        let duration = self.duration();
+        if duration == Duration::from_secs(0) {
+            return false;
+        }
+
+        let mut position = if self.looping {
+            let nanos = elapsed.as_nanos() % duration.as_nanos();
+            Duration::new((nanos / 1_000_000_000) as u64, (nanos % 1_000_000_000) as u32)
+        } else if elapsed >= duration {
+            return false;
+        } else {
+            elapsed
+        };
+
+        for &(on, length) in &self.segments {
+            if position < length {
+                return on;
+            }
+            position -= length;
+        }
+
+        false
+    }
+}
+
+/// Plays a `RumblePattern` on each port, driven by the time elapsed between frames.
+///
+/// `advance` computes the motor states, which can be passed directly to `Listener::rumble`.
+/// Identical rumble commands are not sent to the adapter again, so this is cheap to do every
+/// frame.
+#[derive(Clone, Debug, Default)]
+pub struct RumblePlayer {
+    playing: [Option<(RumblePattern, Duration)>; 4],
+}
+
+impl RumblePlayer {
+    /// Returns a player with nothing playing.
+    pub fn new() -> RumblePlayer {
+        RumblePlayer::default()
+    }
+
+    /// Starts playing `pattern` on `port` from its beginning, replacing any pattern already
+    /// playing there.
+    pub fn play(&mut self, port: Port, pattern: RumblePattern) {
+        self.playing[port.index()] = Some((pattern, Duration::from_secs(0)));
+    }
+
+    /// Stops the pattern playing on `port`, if any.
+    pub fn stop(&mut self, port: Port) {
+        self.playing[port.index()] = None;
+    }
+
+    /// Returns `true` if a pattern is playing on `port`.
+    pub fn is_playing(&self, port: Port) -> bool {
+        self.playing[port.index()].is_some()
+    }
+
+    /// Returns whether the motor of each port should currently be on.
+    pub fn state(&self) -> [bool; 4] {
+        let mut state = [false; 4];
+        for (on, playing) in state.iter_mut().zip(self.playing.iter()) {
+            if let Some((ref pattern, elapsed)) = *playing {
+                *on = pattern.state_at(elapsed);
+            }
+        }
+        state
+    }
+
+    /// Moves every playing pattern forward by `delta` and returns the resulting motor states.
+    ///
+    /// One-shot patterns that have finished are removed, leaving their ports off.
+    pub fn advance(&mut self, delta: Duration) -> [bool; 4] {
+        for playing in self.playing.iter_mut() {
+            let finished = match *playing {
+                Some((ref pattern, ref mut elapsed)) => {
+                    *elapsed += delta;
+                    pattern.is_finished(*elapsed)
+                },
+                None => false,
+            };
+
+            if finished {
+                *playing = None;
+            }
+        }
+
+        self.state()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buzzes() -> Vec<(bool, Duration)> {
+        let ms = Duration::from_millis;
+        vec![(true, ms(100)), (false, ms(50)), (true, ms(100))]
+    }
+
+    #[test]
+    fn one_shot_pattern() {
+        let ms = Duration::from_millis;
+        let pattern = RumblePattern::once(buzzes());
+        assert_eq!(pattern.duration(), ms(250));
+
+        let states: Vec<_> = [0, 99, 100, 149, 150, 249, 250, 400].iter()
+            .map(|&t| pattern.state_at(ms(t)))
+            .collect();
+        assert_eq!(states, vec![true, true, false, false, true, true, false, false]);
+        assert!(!pattern.is_finished(ms(249)));
+        assert!(pattern.is_finished(ms(250)));
+    }
+
+    #[test]
+    fn looping_pattern() {
+        let ms = Duration::from_millis;
+        let pattern = RumblePattern::looping(buzzes());
+
+        let states: Vec<_> = [250, 349, 350, 400, 520].iter()
+            .map(|&t| pattern.state_at(ms(t)))
+            .collect();
+        assert_eq!(states, vec![true, true, false, true, true]);
+        assert!(!pattern.is_finished(ms(10_000)));
+    }
+
+    #[test]
+    fn player_steps_through_segments() {
+        let ms = Duration::from_millis;
+        let mut player = RumblePlayer::new();
+        player.play(Port::Two, RumblePattern::once(buzzes()));
+
+        assert_eq!(player.state(), [false, true, false, false]);
+        assert_eq!(player.advance(ms(100)), [false, false, false, false]);
+        assert_eq!(player.advance(ms(50)), [false, true, false, false]);
+        assert!(player.is_playing(Port::Two));
+        assert_eq!(player.advance(ms(100)), [false, false, false, false]);
+        assert!(!player.is_playing(Port::Two));
+    }
+}
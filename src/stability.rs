@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use {Axis, Controller, Port, TimedFrame};
+
+/// Tracks whether the analog inputs of each port have been at rest for a while, for example to
+/// decide when to capture a neutral calibration ("hold still... captured").
+pub struct StabilityDetector {
+    history: Duration,
+    samples: [VecDeque<(Instant, Controller)>; 4],
+}
+
+impl StabilityDetector {
+    /// Returns a detector that remembers frames for `history`, which bounds the longest window
+    /// that `is_stable` can check.
+    pub fn new(history: Duration) -> StabilityDetector {
+        StabilityDetector {
+            history: history,
+            samples: [VecDeque::new(), VecDeque::new(), VecDeque::new(), VecDeque::new()],
+        }
+    }
+
+    /// Records a frame, such as one returned by `Listener::read_timed`. Frames must be fed in
+    /// chronological order.
+    ///
+    /// A port that is empty in `frame` forgets its history, so a reconnected controller must be
+    /// held still for the whole window again.
+    pub fn update(&mut self, frame: &TimedFrame) {
+        for (samples, controller) in self.samples.iter_mut().zip(frame.controllers.iter()) {
+            let controller = match *controller {
+                Some(controller) => controller,
+                None => {
+                    samples.clear();
+                    continue;
+                },
+            };
+
+            samples.push_back((frame.at, controller));
+
+            // Keep the newest sample at or before the cutoff, as it marks the state at the start
+            // of the longest window.
+            if let Some(cutoff) = frame.at.checked_sub(self.history) {
+                while samples.len() > 1 && samples[1].0 <= cutoff {
+                    samples.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if none of the analog values of the controller on `port` have strayed by
+    /// more than `tolerance` from their latest values for the last `window`, measured back from
+    /// the latest recorded frame.
+    ///
+    /// `false` is returned if the port has not been occupied for at least `window`, including when
+    /// `window` exceeds the history passed to `new`.
+    pub fn is_stable(&self, port: Port, window: Duration, tolerance: u8) -> bool {
+        let samples = &self.samples[port.index()];
+        let &(latest_at, latest) = match samples.back() {
+            Some(latest) => latest,
+            None => return false,
+        };
+        let start = match latest_at.checked_sub(window) {
+            Some(start) => start,
+            None => return false,
+        };
+        let first = match samples.iter().rposition(|&(at, _)| at <= start) {
+            Some(first) => first,
+            None => return false,
+        };
+
+        samples.iter().skip(first).all(|&(_, controller)| {
+            Axis::ALL.iter().all(|&axis| {
+                let (value, latest) = (controller.axis(axis), latest.axis(axis));
+                let delta = if value > latest { value - latest } else { latest - value };
+                delta <= tolerance
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ControllerBuilder;
+
+    fn frame(at: Instant, stick_x: u8) -> TimedFrame {
+        let controller = ControllerBuilder::new().stick(stick_x, 127).build();
+        TimedFrame { at: at, controllers: [Some(controller), None, None, None] }
+    }
+
+    #[test]
+    fn stable_then_jittery() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut detector = StabilityDetector::new(ms(1000));
+
+        for step in 0..6 {
+            detector.update(&frame(start + ms(step * 100), 127 + (step % 2) as u8));
+        }
+        assert!(detector.is_stable(Port::One, ms(500), 1));
+        assert!(!detector.is_stable(Port::One, ms(600), 1));
+        assert!(!detector.is_stable(Port::Two, ms(500), 1));
+
+        detector.update(&frame(start + ms(600), 140));
+        assert!(!detector.is_stable(Port::One, ms(500), 1));
+        assert!(detector.is_stable(Port::One, ms(500), 13));
+    }
+}
@@ -0,0 +1,82 @@
+use {Controllers, Port};
+
+/// An identifier assigned by a `PortTracker` to a controller for as long as it stays connected.
+///
+/// Identifiers are only unique among those issued by the same tracker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ControllerId(u64);
+
+/// Assigns an identity to the controller on each port, so that applications can tell whether the
+/// controller on a port is still the same one, for example to keep a player's slot.
+///
+/// The protocol carries no serial number or other hardware identity, so identity is inferred per
+/// session and per port: a controller keeps its `ControllerId` for as long as its port stays
+/// occupied, and a new one is issued whenever a port becomes occupied, even if the same physical
+/// controller is plugged back in. A controller moved to another port is likewise treated as a new
+/// one. Identifiers are not persisted and start over with every tracker.
+#[derive(Clone, Debug, Default)]
+pub struct PortTracker {
+    ids: [Option<ControllerId>; 4],
+    next: u64,
+}
+
+impl PortTracker {
+    /// Returns a tracker that has not seen any controller yet.
+    pub fn new() -> PortTracker {
+        PortTracker::default()
+    }
+
+    /// Records a frame, such as one returned by `Listener::read`. Every frame should be fed in
+    /// order, as a disconnect that is never seen keeps the controller's identity alive.
+    ///
+    /// Ports that became occupied are assigned a new identifier, and ports that became empty lose
+    /// theirs.
+    pub fn update(&mut self, controllers: &Controllers) {
+        for (id, controller) in self.ids.iter_mut().zip(controllers.0.iter()) {
+            match (*id, *controller) {
+                (None, Some(_)) => {
+                    *id = Some(ControllerId(self.next));
+                    self.next += 1;
+                },
+                (Some(_), None) => *id = None,
+                _ => {},
+            }
+        }
+    }
+
+    /// Returns the identifier of the controller on `port`, or `None` if the port was empty in the
+    /// latest frame.
+    pub fn id_for(&self, port: Port) -> Option<ControllerId> {
+        self.ids[port.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ControllerBuilder;
+
+    #[test]
+    fn replug_issues_new_id() {
+        let controller = Some(ControllerBuilder::new().build());
+        let mut tracker = PortTracker::new();
+        assert_eq!(tracker.id_for(Port::One), None);
+
+        tracker.update(&Controllers([controller, controller, None, None]));
+        let first = tracker.id_for(Port::One).unwrap();
+        let other = tracker.id_for(Port::Two).unwrap();
+        assert!(first != other);
+
+        tracker.update(&Controllers([controller, controller, None, None]));
+        assert_eq!(tracker.id_for(Port::One), Some(first));
+
+        tracker.update(&Controllers([None, controller, None, None]));
+        assert_eq!(tracker.id_for(Port::One), None);
+        assert_eq!(tracker.id_for(Port::Two), Some(other));
+
+        tracker.update(&Controllers([controller, controller, None, None]));
+        let replugged = tracker.id_for(Port::One).unwrap();
+        assert!(replugged != first && replugged != other);
+        assert_eq!(tracker.id_for(Port::Two), Some(other));
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+use {Controller, Error, Listener};
+
+/// Keeps track of when a `Listener` last produced a valid packet.
+///
+/// An adapter can stall without reporting a hard error, for example by timing out on every read
+/// while its interface remains claimed. The watchdog makes such a stall observable so that the
+/// application can decide to reconnect.
+pub struct Watchdog<'a> {
+    listener: Listener<'a>,
+    heartbeat: Heartbeat,
+}
+
+impl<'a> Watchdog<'a> {
+    /// Wraps a `Listener`. The moment of wrapping counts as the last successful read.
+    pub fn new(listener: Listener<'a>) -> Watchdog<'a> {
+        Watchdog {
+            listener: listener,
+            heartbeat: Heartbeat::new(Instant::now()),
+        }
+    }
+
+    /// Reads a data packet through `Listener::read`, recording the time if it succeeds.
+    pub fn read(&mut self) -> Result<[Option<Controller>; 4], Error> {
+        let controllers = try!(self.listener.read());
+        self.heartbeat.beat(Instant::now());
+        Ok(controllers)
+    }
+
+    /// Returns the time elapsed since the last successful read.
+    pub fn time_since_last(&self) -> Duration {
+        self.heartbeat.since(Instant::now())
+    }
+
+    /// Returns `true` if no successful read has occurred for longer than `threshold`.
+    pub fn is_stalled(&self, threshold: Duration) -> bool {
+        self.is_stalled_at(Instant::now(), threshold)
+    }
+
+    /// Returns `true` if, as of `now`, no successful read has occurred for longer than
+    /// `threshold`.
+    ///
+    /// This is `is_stalled` with the current time supplied by the caller, for example to check
+    /// several watchdogs against the same moment. A `now` that precedes the last successful read
+    /// counts as no time having elapsed.
+    pub fn is_stalled_at(&self, now: Instant, threshold: Duration) -> bool {
+        self.heartbeat.is_stalled_at(now, threshold)
+    }
+
+    /// Returns the wrapped `Listener`.
+    pub fn into_inner(self) -> Listener<'a> {
+        self.listener
+    }
+}
+
+// The time keeping of `Watchdog`, kept apart from the `Listener` so that it can be driven by
+// arbitrary instants.
+#[derive(Clone, Copy, Debug)]
+struct Heartbeat {
+    last: Instant,
+}
+
+impl Heartbeat {
+    fn new(now: Instant) -> Heartbeat {
+        Heartbeat { last: now }
+    }
+
+    fn beat(&mut self, now: Instant) {
+        self.last = now;
+    }
+
+    fn since(&self, now: Instant) -> Duration {
+        now.checked_duration_since(self.last).unwrap_or(Duration::from_secs(0))
+    }
+
+    fn is_stalled_at(&self, now: Instant, threshold: Duration) -> bool {
+        self.since(now) > threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stalls_after_threshold() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut heartbeat = Heartbeat::new(start);
+
+        assert!(!heartbeat.is_stalled_at(start + ms(500), ms(500)));
+        assert!(heartbeat.is_stalled_at(start + ms(501), ms(500)));
+
+        heartbeat.beat(start + ms(600));
+        assert_eq!(heartbeat.since(start + ms(700)), ms(100));
+        assert!(!heartbeat.is_stalled_at(start + ms(700), ms(500)));
+        assert!(!heartbeat.is_stalled_at(start, ms(0)));
+    }
+}